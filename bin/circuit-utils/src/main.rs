@@ -1,7 +1,18 @@
-use anyhow::Result;
-use circuit_lib::Circuit;
-use clap::{Parser, Subcommand};
-use std::{fs::File, path::PathBuf};
+use anyhow::{Context, Result};
+use circuit_lib::{
+    Circuit, GateType, WireId,
+    equiv::{EquivConfig, Equivalence},
+    export::View,
+    format::Format,
+    random::{FanOut, RandomCircuitConfig, Shape},
+    sim::{ValueFormat, parse_value},
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
 #[derive(Parser)]
 #[command(name = "circuit-tool", version)]
@@ -29,7 +40,304 @@ enum Commands {
         /// Ratio of XOR gates to AND gates
         #[arg(short, long, value_name = "RATIO")]
         ratio_xor_to_and: f64,
+
+        /// Seed for the generator (a fresh one is picked and printed if omitted)
+        #[arg(short, long, value_name = "SEED")]
+        seed: Option<u64>,
+
+        /// Fraction of INV gates, the gates that are neither XOR nor INV are AND
+        #[arg(long, value_name = "RATIO", default_value_t = 0.0)]
+        inv_ratio: f64,
+
+        /// Spread the gates over this many layers
+        #[arg(long, value_name = "DEPTH", conflicts_with = "width")]
+        depth: Option<usize>,
+
+        /// Put this many gates in each layer
+        #[arg(long, value_name = "WIDTH")]
+        width: Option<usize>,
+
+        /// Number of output wires, taken from the last gates (all unread wires if omitted)
+        #[arg(long = "outputs", value_name = "NUM_OUTPUTS")]
+        num_outputs: Option<usize>,
+
+        /// How gate inputs are picked: `uniform`, `unused:<probability>` or `window:<n>`
+        #[arg(long, value_name = "DIST", default_value = "uniform")]
+        fan_out: FanOut,
+
+        /// Number of inputs owned by the garbler, the rest go to the evaluator
+        #[arg(long, value_name = "NUM_INPUT")]
+        garbler_inputs: Option<usize>,
+    },
+
+    /// Fold constants, merge duplicate gates and remove dead gates of a bristol fashion circuit
+    Optimize {
+        /// bristol fashion or binary circuit to optimize
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// path to write the optimized circuit
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+
+        /// Also remove INV gates, folding them into the gates that read them
+        #[arg(long)]
+        absorb_inv: bool,
+
+        /// Also rewrite small cones to use fewer AND gates
+        #[arg(long)]
+        reduce_and: bool,
+    },
+
+    /// Print gate counts, depth, width, live wires, fan-out and guest cost estimates
+    Stats {
+        /// bristol fashion or binary circuit to analyze
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+    },
+
+    /// Render a bristol fashion circuit as a Graphviz graph or a JSON netlist
+    Export {
+        /// bristol fashion or binary circuit to export
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Dot)]
+        format: ExportFormat,
+
+        /// path to write to (stdout if omitted)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// Only gates at most this many gates away from the inputs
+        #[arg(long, value_name = "DEPTH")]
+        depth: Option<usize>,
+
+        /// Only gates near these wires (comma separated)
+        #[arg(long, value_name = "WIRES", value_delimiter = ',')]
+        around: Vec<WireId>,
+
+        /// How many gates away from `--around` wires to go
+        #[arg(long, value_name = "GATES", default_value_t = 2)]
+        radius: usize,
+    },
+
+    /// Evaluate a bristol fashion circuit on plaintext inputs
+    Eval {
+        /// bristol fashion or binary circuit to evaluate
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// One number per input value (decimal, 0x hex or 0b binary), the first wire of a
+        /// value being its least significant bit
+        #[arg(value_name = "VALUES")]
+        values: Vec<String>,
+
+        /// How to print the outputs: `bits` (wire order), `hex` or `int`
+        #[arg(short, long, value_name = "FORMAT", default_value = "hex")]
+        format: ValueFormat,
     },
+
+    /// Generate a hash function circuit from AND, XOR and INV gates
+    Hash {
+        #[arg(value_enum, value_name = "FUNCTION")]
+        function: HashFunction,
+
+        /// Message length in bytes (in 64-byte blocks for `sha256-blocks`)
+        #[arg(short, long, value_name = "LEN")]
+        len: usize,
+
+        /// path to write the circuit
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+
+    /// Chain bristol fashion circuits, each feeding its outputs into the first input values of
+    /// the next, or place them side by side
+    Compose {
+        /// circuits to combine, in order
+        #[arg(value_name = "INPUTS", num_args = 2..)]
+        inputs: Vec<PathBuf>,
+
+        /// Place the circuits side by side instead of chaining them
+        #[arg(long)]
+        parallel: bool,
+
+        /// path to write the combined circuit
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+
+    /// Import a combinational netlist synthesized by Yosys as a bristol fashion circuit
+    Import {
+        /// Yosys JSON (`write_json`) or BLIF (`write_blif`) netlist
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Netlist format (guessed from the file extension if omitted)
+        #[arg(short, long, value_enum)]
+        format: Option<NetlistFormat>,
+
+        /// Module to import from a Yosys JSON netlist with several modules
+        #[arg(long, value_name = "MODULE")]
+        top: Option<String>,
+
+        /// path to write the circuit
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+
+    /// Sort the gates of a circuit into an evaluation order that keeps few wires live and
+    /// renumber the wires densely
+    Reorder {
+        /// bristol fashion or binary circuit, its gates in any order
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// path to write the reordered circuit
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+
+    /// Check that two circuits with the same inputs and outputs compute the same function
+    Equiv {
+        /// bristol fashion or binary circuits to compare
+        #[arg(value_name = "LEFT")]
+        left: PathBuf,
+
+        #[arg(value_name = "RIGHT")]
+        right: PathBuf,
+
+        /// Try every input if there are at most this many input wires
+        #[arg(long, value_name = "NUM_INPUT", default_value_t = 20)]
+        exhaustive_limit: usize,
+
+        /// Number of random passes of 64 inputs each otherwise
+        #[arg(long, value_name = "ROUNDS", default_value_t = 1024)]
+        rounds: usize,
+
+        /// Seed for the random inputs
+        #[arg(short, long, value_name = "SEED", default_value_t = 0)]
+        seed: u64,
+
+        /// Also write a DIMACS CNF miter, unsatisfiable iff the circuits are equivalent
+        #[arg(long, value_name = "CNF")]
+        cnf: Option<PathBuf>,
+    },
+
+    /// Convert a circuit between bristol fashion, legacy bristol, simplified bristol, headerless
+    /// bristol and the compact binary format
+    Convert {
+        /// circuit to convert
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Format to read, told from the contents if omitted
+        #[arg(short, long, value_enum)]
+        from: Option<CircuitFormat>,
+
+        /// Format to write
+        #[arg(short, long, value_enum)]
+        to: CircuitFormat,
+
+        /// Where the inputs and outputs come from. A headerless circuit has only its topology
+        #[arg(long, value_enum, default_value = "header")]
+        io: IoSource,
+
+        /// Keep the gates as they are when writing binary. By default constants are folded and
+        /// INV gates absorbed first, as the garbler does, so that the file hashes to the circuit
+        /// hash
+        #[arg(long)]
+        raw: bool,
+
+        /// path to write the circuit
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum NetlistFormat {
+    /// Yosys `write_json`
+    Json,
+    Blif,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CircuitFormat {
+    Bristol,
+    /// Bristol before Bristol Fashion, with two input values and one output value
+    Legacy,
+    /// `OP in0 ... out` gate lines under a gate and wire count header
+    Simplified,
+    /// Bristol Fashion gate lines only
+    Headerless,
+    /// `circuit_lib::binary`, the encoding the guest hashes
+    Binary,
+}
+
+impl From<CircuitFormat> for Format {
+    fn from(format: CircuitFormat) -> Format {
+        match format {
+            CircuitFormat::Bristol => Format::Bristol,
+            CircuitFormat::Legacy => Format::Legacy,
+            CircuitFormat::Simplified => Format::Simplified,
+            CircuitFormat::Headerless => Format::Headerless,
+            CircuitFormat::Binary => Format::Binary,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IoSource {
+    /// The I/O sizes declared in the header, inputs first and outputs last
+    Header,
+    /// The wires no gate drives as inputs, the driven wires no gate reads as outputs
+    Topology,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HashFunction {
+    /// SHA-256 of a message, padding included
+    Sha256,
+    /// SHA-256 compression of already padded blocks
+    Sha256Blocks,
+    /// BLAKE3 of a message
+    Blake3,
+}
+
+/// Read a circuit in any format, sorting its gates into evaluation order (see
+/// [`Circuit::reorder`]) if they aren't
+fn load_circuit(path: &Path) -> Result<Circuit> {
+    let circuit = Circuit::from_file(path)?;
+    if circuit.is_in_evaluation_order() {
+        Ok(circuit)
+    } else {
+        circuit
+            .reorder()
+            .with_context(|| format!("failed to sort the gates of `{}`", path.display()))
+    }
+}
+
+/// One line summary of the gate mix and wire count
+fn gate_summary(ckt: &Circuit) -> String {
+    let count = |f: fn(GateType) -> bool| ckt.gates.iter().filter(|g| f(g.gate_type)).count();
+    let and = count(|t| t == GateType::And);
+    let xor = count(|t| t == GateType::Xor);
+    let inv = count(|t| t == GateType::Inv);
+    let and_like = count(GateType::is_and_like);
+    format!(
+        "{} gates ({and} AND, {xor} XOR, {inv} INV, {} other; {and_like} AND-like), {} wires",
+        ckt.get_gate_count(),
+        ckt.get_gate_count() - and - xor - inv,
+        ckt.get_wire_count()
+    )
 }
 
 fn main() -> Result<()> {
@@ -41,15 +349,295 @@ fn main() -> Result<()> {
             num_inputs,
             output,
             ratio_xor_to_and,
+            seed,
+            inv_ratio,
+            depth,
+            width,
+            num_outputs,
+            fan_out,
+            garbler_inputs,
         } => {
-            let circuit = Circuit::random(num_inputs, num_gates, ratio_xor_to_and);
+            let seed = seed.unwrap_or_else(rand::random);
+            let shape = match (depth, width) {
+                (Some(d), _) => Shape::Depth(d),
+                (_, Some(w)) => Shape::Width(w),
+                _ => Shape::Unlayered,
+            };
+            let circuit = Circuit::random_with(&RandomCircuitConfig {
+                num_inputs,
+                garbler_inputs,
+                num_gates,
+                xor_ratio: ratio_xor_to_and,
+                inv_ratio,
+                shape,
+                num_outputs,
+                fan_out,
+                seed,
+            })?;
 
             let file = File::create(&output)
                 .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
 
             circuit.write_bristol_fashion(file)?;
 
-            println!("Wrote random circuit to {}", output.display());
+            println!("Wrote random circuit to {} (seed {seed})", output.display());
+        }
+        Commands::Optimize {
+            input,
+            output,
+            absorb_inv,
+            reduce_and,
+        } => {
+            let circuit = load_circuit(&input)?;
+            let mut optimized = if reduce_and {
+                circuit.reduce_and_gates()?
+            } else {
+                circuit.simplify()?
+            };
+            if absorb_inv {
+                // not simplified again, folding would turn `x XOR 1` back into an INV
+                optimized = optimized.absorb_inverters().with_standard_layout()?;
+            }
+
+            let file = File::create(&output)
+                .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
+            optimized.write_bristol_fashion(file)?;
+
+            println!("before: {}", gate_summary(&circuit));
+            println!("after:  {}", gate_summary(&optimized));
+            println!("Wrote optimized circuit to {}", output.display());
+        }
+        Commands::Stats { input } => {
+            let circuit = load_circuit(&input)?;
+            println!("{}", circuit.stats()?);
+        }
+        Commands::Export {
+            input,
+            format,
+            output,
+            depth,
+            around,
+            radius,
+        } => {
+            let circuit = load_circuit(&input)?;
+            let view = View {
+                max_depth: depth,
+                around,
+                radius,
+            };
+            let out: Box<dyn Write> = match &output {
+                Some(path) => Box::new(
+                    File::create(path)
+                        .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", path.display(), e))?,
+                ),
+                None => Box::new(io::stdout().lock()),
+            };
+            match format {
+                ExportFormat::Dot => circuit.write_dot(out, &view)?,
+                ExportFormat::Json => circuit.write_json(out, &view)?,
+            }
+        }
+        Commands::Eval {
+            input,
+            values,
+            format,
+        } => {
+            let circuit = load_circuit(&input)?;
+            anyhow::ensure!(
+                values.len() == circuit.input_groups.len(),
+                "the circuit takes {} input values of {:?} bits, got {}",
+                circuit.input_groups.len(),
+                circuit.input_groups,
+                values.len()
+            );
+            let inputs = values
+                .iter()
+                .zip(&circuit.input_groups)
+                .map(|(v, &width)| parse_value(v, width))
+                .collect::<Result<Vec<_>>>()?;
+            for output in circuit.eval(&inputs)? {
+                println!("{}", format.format(&output));
+            }
+        }
+        Commands::Hash {
+            function,
+            len,
+            output,
+        } => {
+            let circuit = match function {
+                HashFunction::Sha256 => Circuit::sha256(len)?,
+                HashFunction::Sha256Blocks => Circuit::sha256_blocks(len)?,
+                HashFunction::Blake3 => Circuit::blake3(len)?,
+            };
+
+            let file = File::create(&output)
+                .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
+            circuit.write_bristol_fashion(file)?;
+
+            println!("{}", gate_summary(&circuit));
+            println!("Wrote hash circuit to {}", output.display());
+        }
+        Commands::Compose {
+            inputs,
+            parallel,
+            output,
+        } => {
+            let mut circuit = load_circuit(&inputs[0])?;
+            for path in &inputs[1..] {
+                let next = load_circuit(path)?;
+                circuit = if parallel {
+                    circuit.parallel(&next)?
+                } else {
+                    circuit
+                        .then(&next)
+                        .with_context(|| format!("couldn't feed into {}", path.display()))?
+                };
+            }
+
+            let file = File::create(&output)
+                .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
+            circuit.write_bristol_fashion(file)?;
+
+            println!("{}", gate_summary(&circuit));
+            println!(
+                "inputs {:?}, outputs {:?}",
+                circuit.input_groups, circuit.output_groups
+            );
+            println!("Wrote combined circuit to {}", output.display());
+        }
+        Commands::Import {
+            input,
+            format,
+            top,
+            output,
+        } => {
+            let format = match format {
+                Some(format) => format,
+                None => match input.extension().and_then(|e| e.to_str()) {
+                    Some("json") => NetlistFormat::Json,
+                    Some("blif") => NetlistFormat::Blif,
+                    _ => anyhow::bail!(
+                        "can't tell the format of {}, pass --format",
+                        input.display()
+                    ),
+                },
+            };
+            let circuit = match format {
+                NetlistFormat::Json => Circuit::from_yosys_json(&input, top.as_deref())?,
+                NetlistFormat::Blif => Circuit::from_blif(&input)?,
+            };
+
+            let file = File::create(&output)
+                .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
+            circuit.write_bristol_fashion(file)?;
+
+            println!("{}", gate_summary(&circuit));
+            println!(
+                "inputs {:?}, outputs {:?}",
+                circuit.input_groups, circuit.output_groups
+            );
+            println!("Wrote imported circuit to {}", output.display());
+        }
+
+        Commands::Reorder { input, output } => {
+            let circuit = Circuit::from_file(&input)?;
+            let reordered = circuit.reorder()?;
+
+            let file = File::create(&output)
+                .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
+            reordered.write_bristol_fashion(file)?;
+
+            if circuit.is_in_evaluation_order() {
+                println!(
+                    "peak live wires: {} -> {}",
+                    circuit.stats()?.peak_live_wires,
+                    reordered.stats()?.peak_live_wires
+                );
+            } else {
+                println!("peak live wires: {}", reordered.stats()?.peak_live_wires);
+            }
+            println!("wires: {} -> {}", circuit.num_wires, reordered.num_wires);
+            println!("Wrote reordered circuit to {}", output.display());
+        }
+
+        Commands::Equiv {
+            left,
+            right,
+            exhaustive_limit,
+            rounds,
+            seed,
+            cnf,
+        } => {
+            let a = load_circuit(&left)?;
+            let b = load_circuit(&right)?;
+            if let Some(cnf) = cnf {
+                let file = File::create(&cnf)
+                    .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", cnf.display(), e))?;
+                a.write_miter_cnf(&b, file)?;
+                println!("Wrote miter to {}", cnf.display());
+            }
+
+            let config = EquivConfig {
+                exhaustive_limit,
+                random_rounds: rounds,
+                seed,
+            };
+            match a.check_equivalence(&b, &config)? {
+                Equivalence::Equivalent {
+                    exhaustive: true,
+                    checked,
+                } => println!("equivalent on all {checked} inputs"),
+                Equivalence::Equivalent {
+                    exhaustive: false,
+                    checked,
+                } => println!("no difference found on {checked} random inputs"),
+                Equivalence::Counterexample {
+                    inputs,
+                    left: left_out,
+                    right: right_out,
+                } => {
+                    let hex = |values: &[Vec<bool>]| {
+                        values
+                            .iter()
+                            .map(|v| ValueFormat::Hex.format(v))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    };
+                    println!("inputs:  {}", hex(&inputs));
+                    println!("{}: {}", left.display(), hex(&left_out));
+                    println!("{}: {}", right.display(), hex(&right_out));
+                    anyhow::bail!("the circuits differ");
+                }
+            }
+        }
+
+        Commands::Convert {
+            input,
+            from,
+            to,
+            io: io_source,
+            raw,
+            output,
+        } => {
+            let from = match from {
+                Some(format) => format.into(),
+                None => Format::detect(&input)?,
+            };
+            let mut circuit = Circuit::from_file_as(&input, from)?;
+            if let IoSource::Topology = io_source {
+                circuit = circuit.with_io_from_topology();
+            }
+            let to = Format::from(to);
+            if to == Format::Binary && !raw {
+                circuit = circuit.into_garbling_form()?;
+            }
+
+            let file = File::create(&output)
+                .map_err(|e| anyhow::anyhow!("couldn't open {}: {}", output.display(), e))?;
+            circuit.write_as(file, to)?;
+
+            println!("{}", gate_summary(&circuit));
+            println!("Wrote converted circuit to {}", output.display());
         }
     }
     Ok(())
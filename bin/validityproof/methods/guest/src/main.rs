@@ -2,7 +2,6 @@ use garble::garble::{garble_ckt, gen_label_hash};
 use garble::input::{Circuit, LabelInputs};
 use risc0_zkvm::guest::env;
 use rkyv::{api::high::to_bytes_with_alloc, deserialize, rancor::Error, ser::allocator::Arena};
-use sha2::{Digest, Sha256};
 use validityproof_core::GuestOutput;
 
 fn main() {
@@ -15,11 +14,6 @@ fn main() {
     let mut circuit_bytes = vec![0u8; circuit_size as usize];
     env::read_slice(&mut circuit_bytes);
 
-    // Hash the circuit bytes BEFORE deserializing to save memory
-    let mut hasher = Sha256::new();
-    hasher.update(&circuit_bytes);
-    let circuit_hash: [u8; 32] = hasher.finalize().into();
-
     // Read labels size and labels bytes
     let mut labels_size_bytes = vec![0u8; 4];
     env::read_slice(&mut labels_size_bytes);
@@ -40,9 +34,14 @@ fn main() {
         rkyv::access::<rkyv::Archived<LabelInputs>, Error>(&labels_bytes[..]).unwrap();
     let labels = deserialize::<LabelInputs, Error>(labels_archived).unwrap();
 
-    // Drop the serialized circuit bytes to save memory (we only need the hash now)
+    // Drop the serialized circuit bytes to save memory
     drop(circuit_bytes);
 
+    // Hash the canonical encoding of the circuit rather than the rkyv bytes. The host sends the
+    // circuit in its garbling form, so the commitment is the SHA-256 of the binary file written
+    // by `circuit-utils convert --to binary`, or `garble::digest::circuit_digest` of the source
+    let circuit_hash = circuit.canonical_hash();
+
     // compute hash of the input labels
     let label_hashes = gen_label_hash(&labels.input_labels);
 
@@ -44,6 +44,9 @@ fn main() {
     let xor_gate_count = input_ckt.xor_gate_count;
     let gate_count = input_ckt.gates.len();
 
+    // the hash the guest is expected to commit to, computed from the canonical encoding
+    let circuit_hash = input_ckt.canonical_hash();
+
     // compute the delta, input labels and inner label (output of gates other than XOR) using seed
     let labels = gen_labels(seed, input_wire_count, inner_wire_count);
 
@@ -137,9 +140,15 @@ fn main() {
         rkyv::access::<ArchivedGuestOutput, Error>(&public_values_bytes[..]).unwrap();
 
     // deserialize inputs
-    let _public_values: GuestOutput =
+    let public_values: GuestOutput =
         deserialize::<GuestOutput, Error>(public_values_archieved).unwrap();
 
+    // the committed circuit hash must match the one recomputed from the bristol file
+    assert_eq!(
+        public_values.circuit_hash, circuit_hash,
+        "guest committed to a different circuit"
+    );
+
     // The receipt was verified at the end of proving, but the below code is an
     // example of how someone else could verify this receipt.
     receipt.verify(FREEXORGARBLE_ID).unwrap();
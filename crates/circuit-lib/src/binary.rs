@@ -0,0 +1,365 @@
+//! Binary circuit files, in the canonical encoding the garbling guest hashes.
+//!
+//! A binary file is exactly the canonical encoding of `garble::digest`, so loading one is a
+//! matter of reading fixed-size records and its SHA-256 is the circuit hash. All integers are
+//! little-endian `u32`:
+//!
+//! ```text
+//! CIRCUIT_DOMAIN                      ASCII domain separator, no length prefix
+//! total_wire_count                    u32
+//! input1_count                        u32  garbler input wires (the first input value)
+//! input2_count                        u32  evaluator input wires (the other input values)
+//! output_wire_count                   u32
+//! gate_count                          u32
+//! gates, in circuit order:
+//!   OP_AND in0 in1 out                u8 followed by 3 x u32
+//!   OP_XOR in0 in1 out                u8 followed by 3 x u32
+//!   OP_INV input out                  u8 followed by 2 x u32
+//!   OP_CONST value out                u8, u8 (0 or 1), u32
+//!   OP_BUF input out                  u8 followed by 2 x u32
+//!   OP_XNOR in0 in1 out               u8 followed by 3 x u32
+//!   OP_LUT truth_table in0 in1 out    u8, u8 (low 4 bits), 3 x u32
+//! ```
+//!
+//! Inputs are the first wires and outputs the last ones, so the counts fully describe the I/O
+//! layout. Other 2-input gates (OR, NAND, ...) are written as `OP_LUT` with their truth table.
+//!
+//! [`CanonicalEncoder`] is the only writer of this layout: [`Circuit::write_binary`] and the
+//! garbler's circuit hash both go through it.
+
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+
+use crate::{Circuit, Gate, GateType, WireId};
+
+/// Domain separator prefixed to every canonical encoding.
+///
+/// The garbler commits to a text circuit in its garbling form (see
+/// [`Circuit::into_garbling_form`]), not to the gates of the file, so the version covers those
+/// passes as well as the layout: any change to the circuits they produce must bump it.
+pub const CIRCUIT_DOMAIN: &[u8] = b"verifiable-garbling/circuit/v2";
+
+/// Opcode of an AND gate in the canonical encoding.
+pub const OP_AND: u8 = 0x01;
+/// Opcode of an XOR gate in the canonical encoding.
+pub const OP_XOR: u8 = 0x02;
+/// Opcode of an INV (NOT) gate in the canonical encoding.
+pub const OP_INV: u8 = 0x03;
+/// Opcode of a constant (Bristol `EQ`) gate in the canonical encoding.
+pub const OP_CONST: u8 = 0x04;
+/// Opcode of a wire copy (Bristol `EQW`) gate in the canonical encoding.
+pub const OP_BUF: u8 = 0x05;
+/// Opcode of an XNOR gate in the canonical encoding.
+pub const OP_XNOR: u8 = 0x06;
+/// Opcode of a truth-table gate in the canonical encoding.
+pub const OP_LUT: u8 = 0x07;
+
+/// Whether `bytes`, the start of a file, begin like a binary circuit
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(CIRCUIT_DOMAIN)
+}
+
+/// Streaming writer of the canonical encoding, feeding it to `emit` piece by piece so that a
+/// large circuit is never copied. Gates must follow the header in circuit order.
+pub struct CanonicalEncoder<F: FnMut(&[u8]) -> io::Result<()>> {
+    emit: F,
+}
+
+impl<F: FnMut(&[u8]) -> io::Result<()>> CanonicalEncoder<F> {
+    /// Start an encoding by writing the domain separator and the header
+    pub fn new(
+        mut emit: F,
+        num_wires: usize,
+        input1_count: usize,
+        input2_count: usize,
+        num_outputs: usize,
+        num_gates: usize,
+    ) -> io::Result<Self> {
+        emit(CIRCUIT_DOMAIN)?;
+        let mut encoder = CanonicalEncoder { emit };
+        for v in [
+            num_wires,
+            input1_count,
+            input2_count,
+            num_outputs,
+            num_gates,
+        ] {
+            encoder.put_u32(v)?;
+        }
+        Ok(encoder)
+    }
+
+    fn put_u32(&mut self, v: usize) -> io::Result<()> {
+        let v = u32::try_from(v).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "wire id or count exceeds u32::MAX",
+            )
+        })?;
+        (self.emit)(&v.to_le_bytes())
+    }
+
+    fn wires(&mut self, prefix: &[u8], wires: &[usize]) -> io::Result<()> {
+        (self.emit)(prefix)?;
+        wires.iter().try_for_each(|&w| self.put_u32(w))
+    }
+
+    /// Write an AND gate
+    pub fn and(&mut self, in0: usize, in1: usize, out: usize) -> io::Result<()> {
+        self.wires(&[OP_AND], &[in0, in1, out])
+    }
+
+    /// Write an XOR gate
+    pub fn xor(&mut self, in0: usize, in1: usize, out: usize) -> io::Result<()> {
+        self.wires(&[OP_XOR], &[in0, in1, out])
+    }
+
+    /// Write an INV gate
+    pub fn inv(&mut self, input: usize, out: usize) -> io::Result<()> {
+        self.wires(&[OP_INV], &[input, out])
+    }
+
+    /// Write a constant gate
+    pub fn constant(&mut self, value: bool, out: usize) -> io::Result<()> {
+        self.wires(&[OP_CONST, u8::from(value)], &[out])
+    }
+
+    /// Write a wire copy gate
+    pub fn buf(&mut self, input: usize, out: usize) -> io::Result<()> {
+        self.wires(&[OP_BUF], &[input, out])
+    }
+
+    /// Write an XNOR gate
+    pub fn xnor(&mut self, in0: usize, in1: usize, out: usize) -> io::Result<()> {
+        self.wires(&[OP_XNOR], &[in0, in1, out])
+    }
+
+    /// Write a truth-table gate
+    pub fn lut(&mut self, truth_table: u8, in0: usize, in1: usize, out: usize) -> io::Result<()> {
+        self.wires(&[OP_LUT, truth_table & 0xF], &[in0, in1, out])
+    }
+}
+
+fn get_u32<R: Read>(r: &mut R) -> io::Result<usize> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf) as usize)
+}
+
+fn get_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+impl Circuit {
+    /// Write the circuit as a binary file. The first input value is the garbler's and the
+    /// others are merged into the evaluator's, and the outputs form a single value. Wires are
+    /// renumbered as for [`Circuit::write_bristol_fashion`] if needed.
+    pub fn write_binary<W: Write>(&self, w: W) -> io::Result<()> {
+        let mut w = BufWriter::new(w);
+        self.encode_canonical(|bytes| w.write_all(bytes))?;
+        w.flush()
+    }
+
+    /// Feed the canonical encoding of the circuit to `emit`, see [`Circuit::write_binary`]
+    pub fn encode_canonical(&self, emit: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        let invalid = |e: anyhow::Error| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let ckt = if self.has_standard_layout() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.with_standard_layout().map_err(invalid)?)
+        };
+        let input1_count = ckt.input_groups.first().copied().unwrap_or(0);
+        if input1_count > ckt.inputs.len() {
+            return Err(invalid(anyhow::anyhow!(
+                "I/O groups don't add up to the number of I/O wires"
+            )));
+        }
+
+        let mut encoder = CanonicalEncoder::new(
+            emit,
+            ckt.num_wires,
+            input1_count,
+            ckt.inputs.len() - input1_count,
+            ckt.outputs.len(),
+            ckt.gates.len(),
+        )?;
+        for g in &ckt.gates {
+            let out = g.output;
+            match (g.gate_type, g.inputs.as_slice()) {
+                (GateType::Inv, &[input]) => encoder.inv(input, out)?,
+                (GateType::Buf, &[input]) => encoder.buf(input, out)?,
+                (GateType::Const(value), &[]) => encoder.constant(value, out)?,
+                (gate_type, &[in0, in1]) if gate_type.arity() == 2 => {
+                    match gate_type
+                        .truth_table()
+                        .expect("2-input gates have a truth table")
+                    {
+                        0x8 => encoder.and(in0, in1, out)?,
+                        0x6 => encoder.xor(in0, in1, out)?,
+                        0x9 => encoder.xnor(in0, in1, out)?,
+                        tt => encoder.lut(tt, in0, in1, out)?,
+                    }
+                }
+                (gate_type, _) => {
+                    return Err(invalid(anyhow::anyhow!(
+                        "malformed {gate_type} gate driving wire {out}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a binary circuit, see [`Circuit::write_binary`]
+    pub fn read_binary<R: Read>(reader: R) -> anyhow::Result<Self> {
+        let mut r = BufReader::with_capacity(1 << 20, reader);
+        let mut domain = vec![0; CIRCUIT_DOMAIN.len()];
+        r.read_exact(&mut domain).context("missing header")?;
+        anyhow::ensure!(is_binary(&domain), "not a binary circuit");
+
+        let num_wires = get_u32(&mut r).context("missing header")?;
+        let input1_count = get_u32(&mut r).context("missing header")?;
+        let input2_count = get_u32(&mut r).context("missing header")?;
+        let num_outputs = get_u32(&mut r).context("missing header")?;
+        let num_gates = get_u32(&mut r).context("missing header")?;
+        let num_inputs = input1_count + input2_count;
+        anyhow::ensure!(
+            num_inputs <= num_wires && num_outputs <= num_wires,
+            "header declares more I/O wires than the circuit has"
+        );
+
+        let wire = |r: &mut BufReader<R>| -> anyhow::Result<WireId> {
+            let w = get_u32(r)?;
+            anyhow::ensure!(w < num_wires, "wire {w} outside 0..{num_wires}");
+            Ok(w)
+        };
+        // don't trust the header with the allocation, the file might be truncated
+        let mut gates = Vec::with_capacity(num_gates.min(1 << 24));
+        for i in 0..num_gates {
+            let gate = (|| {
+                let op = get_u8(&mut r)?;
+                let (gate_type, inputs) = match op {
+                    OP_AND | OP_XOR | OP_XNOR | OP_LUT => {
+                        let gate_type = match op {
+                            OP_AND => GateType::And,
+                            OP_XOR => GateType::Xor,
+                            OP_XNOR => GateType::Xnor,
+                            _ => {
+                                let tt = get_u8(&mut r)?;
+                                anyhow::ensure!(
+                                    tt <= 0xF,
+                                    "truth table {tt:#x} has more than 4 bits"
+                                );
+                                GateType::Lut(tt)
+                            }
+                        };
+                        (gate_type, vec![wire(&mut r)?, wire(&mut r)?])
+                    }
+                    OP_INV => (GateType::Inv, vec![wire(&mut r)?]),
+                    OP_BUF => (GateType::Buf, vec![wire(&mut r)?]),
+                    OP_CONST => match get_u8(&mut r)? {
+                        v @ (0 | 1) => (GateType::Const(v == 1), vec![]),
+                        v => anyhow::bail!("constant {v} is neither 0 nor 1"),
+                    },
+                    op => anyhow::bail!("unknown opcode {op:#04x}"),
+                };
+                Ok(Gate {
+                    gate_type,
+                    inputs,
+                    output: wire(&mut r)?,
+                })
+            })()
+            .with_context(|| format!("gate {i} of {num_gates}"))?;
+            gates.push(gate);
+        }
+        anyhow::ensure!(r.read(&mut [0])? == 0, "trailing bytes after the last gate");
+
+        Ok(Circuit {
+            num_wires,
+            inputs: (0..num_inputs).collect(),
+            outputs: (num_wires - num_outputs..num_wires).collect(),
+            input_groups: if input2_count == 0 {
+                vec![input1_count]
+            } else {
+                vec![input1_count, input2_count]
+            },
+            output_groups: vec![num_outputs],
+            gates,
+        })
+    }
+
+    /// Read the binary circuit file at `path`
+    pub fn from_binary_file(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to read binary circuit `{}`", path.display()))?;
+        Self::read_binary(file)
+            .with_context(|| format!("failed to parse binary circuit `{}`", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{assert_equivalent, circuit, gate};
+
+    #[test]
+    fn round_trips_every_gate_type() {
+        let ckt = circuit(
+            3,
+            vec![
+                gate(GateType::And, &[0, 1], 3),
+                gate(GateType::Xor, &[3, 2], 4),
+                gate(GateType::Inv, &[4], 5),
+                gate(GateType::Const(true), &[], 6),
+                gate(GateType::Buf, &[5], 7),
+                gate(GateType::Xnor, &[7, 6], 8),
+                gate(GateType::Or, &[8, 0], 9),
+                gate(GateType::Lut(0x2), &[9, 1], 10),
+            ],
+            vec![10, 3],
+        );
+        let mut bytes = Vec::new();
+        ckt.write_binary(&mut bytes).unwrap();
+        let back = Circuit::read_binary(bytes.as_slice()).unwrap();
+        assert_equivalent(&ckt, &back);
+        assert_eq!(back.gates[6].gate_type, GateType::Lut(0xE));
+
+        let mut again = Vec::new();
+        back.write_binary(&mut again).unwrap();
+        assert_eq!(again, bytes);
+
+        // 7 gates of 2 or 3 wires and an OR written with its truth table
+        let gate_bytes = 13 + 13 + 9 + 6 + 9 + 13 + 14 + 14;
+        assert_eq!(bytes.len(), CIRCUIT_DOMAIN.len() + 5 * 4 + gate_bytes);
+
+        assert!(Circuit::read_binary(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Circuit::read_binary([bytes.as_slice(), &[0]].concat().as_slice()).is_err());
+        let mut bad_wire = bytes.clone();
+        let last = bad_wire.len() - 4;
+        bad_wire[last..].copy_from_slice(&99u32.to_le_bytes());
+        assert!(Circuit::read_binary(bad_wire.as_slice()).is_err());
+    }
+
+    #[test]
+    fn keeps_the_garbler_and_evaluator_inputs() {
+        let ckt = Circuit::random_with(&crate::random::RandomCircuitConfig {
+            garbler_inputs: Some(3),
+            inv_ratio: 0.1,
+            ..crate::random::RandomCircuitConfig::new(8, 200)
+        })
+        .unwrap();
+        let mut bytes = Vec::new();
+        ckt.write_binary(&mut bytes).unwrap();
+        let back = Circuit::read_binary(bytes.as_slice()).unwrap();
+        assert_eq!(back.input_groups, vec![3, 5]);
+        assert_eq!(back.gates, ckt.with_standard_layout().unwrap().gates);
+    }
+}
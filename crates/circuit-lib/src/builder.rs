@@ -0,0 +1,384 @@
+//! Programmatic construction of circuits from n-bit gadgets.
+//!
+//! Values are slices of wires with the least significant bit first, as in the Bristol Fashion
+//! arithmetic circuits. Gadgets panic when they are given values of different widths.
+
+use crate::{Circuit, Gate, GateType, WireId};
+
+/// Builds a [`Circuit`] gate by gate.
+///
+/// ```
+/// use circuit_lib::builder::CircuitBuilder;
+///
+/// // is a + b at least 1000?
+/// let mut b = CircuitBuilder::new();
+/// let x = b.input(32);
+/// let y = b.input(32);
+/// let sum = b.add(&x, &y);
+/// let threshold = b.constant_word(1000, 32);
+/// let over = b.ge(&sum, &threshold);
+/// b.output(&[over]);
+/// let ckt = b.build().unwrap();
+/// assert_eq!(ckt.get_input_wire_count(), 64);
+/// ```
+#[derive(Debug, Default)]
+pub struct CircuitBuilder {
+    num_wires: usize,
+    inputs: Vec<WireId>,
+    outputs: Vec<WireId>,
+    input_groups: Vec<usize>,
+    output_groups: Vec<usize>,
+    gates: Vec<Gate>,
+    is_input: Vec<bool>,
+    is_output: Vec<bool>,
+    /// wires holding 0 and 1, once needed
+    constants: [Option<WireId>; 2],
+}
+
+impl CircuitBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn new_wire(&mut self) -> WireId {
+        self.num_wires += 1;
+        self.is_input.push(false);
+        self.is_output.push(false);
+        self.num_wires - 1
+    }
+
+    fn gate(&mut self, gate_type: GateType, inputs: Vec<WireId>) -> WireId {
+        let output = self.new_wire();
+        self.gates.push(Gate {
+            gate_type,
+            inputs,
+            output,
+        });
+        output
+    }
+
+    /// Allocate the next input value, `width` wires wide
+    pub fn input(&mut self, width: usize) -> Vec<WireId> {
+        let wires: Vec<WireId> = (0..width).map(|_| self.new_wire()).collect();
+        for &w in &wires {
+            self.is_input[w] = true;
+        }
+        self.inputs.extend(&wires);
+        self.input_groups.push(width);
+        wires
+    }
+
+    /// Mark `wires` as the next output value. Inputs and wires that are already outputs are
+    /// copied to a new wire first.
+    pub fn output(&mut self, wires: &[WireId]) {
+        for &w in wires {
+            let w = if self.is_input[w] || self.is_output[w] {
+                self.gate(GateType::Buf, vec![w])
+            } else {
+                w
+            };
+            self.is_output[w] = true;
+            self.outputs.push(w);
+        }
+        self.output_groups.push(wires.len());
+    }
+
+    /// Finish the circuit, with inputs first and outputs last
+    pub fn build(self) -> anyhow::Result<Circuit> {
+        Circuit {
+            num_wires: self.num_wires,
+            inputs: self.inputs,
+            outputs: self.outputs,
+            input_groups: self.input_groups,
+            output_groups: self.output_groups,
+            gates: self.gates,
+        }
+        .with_standard_layout()
+    }
+
+    /// Wire holding `value`, shared by all users of the constant
+    pub fn constant(&mut self, value: bool) -> WireId {
+        if let Some(w) = self.constants[usize::from(value)] {
+            return w;
+        }
+        let w = self.gate(GateType::Const(value), vec![]);
+        self.constants[usize::from(value)] = Some(w);
+        w
+    }
+
+    /// `width`-bit constant, truncating `value`
+    pub fn constant_word(&mut self, value: u128, width: usize) -> Vec<WireId> {
+        (0..width)
+            .map(|i| self.constant(i < 128 && (value >> i) & 1 == 1))
+            .collect()
+    }
+
+    pub fn xor(&mut self, a: WireId, b: WireId) -> WireId {
+        self.gate(GateType::Xor, vec![a, b])
+    }
+
+    pub fn and(&mut self, a: WireId, b: WireId) -> WireId {
+        self.gate(GateType::And, vec![a, b])
+    }
+
+    pub fn or(&mut self, a: WireId, b: WireId) -> WireId {
+        self.gate(GateType::Or, vec![a, b])
+    }
+
+    pub fn not(&mut self, a: WireId) -> WireId {
+        self.gate(GateType::Inv, vec![a])
+    }
+
+    /// `if_true` if `sel` is set, `if_false` otherwise, with one AND gate
+    pub fn mux(&mut self, sel: WireId, if_true: WireId, if_false: WireId) -> WireId {
+        let diff = self.xor(if_true, if_false);
+        let picked = self.and(sel, diff);
+        self.xor(if_false, picked)
+    }
+
+    pub fn xor_word(&mut self, a: &[WireId], b: &[WireId]) -> Vec<WireId> {
+        check_widths(a, b);
+        a.iter().zip(b).map(|(&a, &b)| self.xor(a, b)).collect()
+    }
+
+    pub fn and_word(&mut self, a: &[WireId], b: &[WireId]) -> Vec<WireId> {
+        check_widths(a, b);
+        a.iter().zip(b).map(|(&a, &b)| self.and(a, b)).collect()
+    }
+
+    pub fn not_word(&mut self, a: &[WireId]) -> Vec<WireId> {
+        a.iter().map(|&a| self.not(a)).collect()
+    }
+
+    /// Bitwise [`mux`](Self::mux) of two values
+    pub fn mux_word(
+        &mut self,
+        sel: WireId,
+        if_true: &[WireId],
+        if_false: &[WireId],
+    ) -> Vec<WireId> {
+        check_widths(if_true, if_false);
+        if_true
+            .iter()
+            .zip(if_false)
+            .map(|(&t, &f)| self.mux(sel, t, f))
+            .collect()
+    }
+
+    /// `a + b + carry` as a value of the same width and the carry out, with one AND gate per bit
+    pub fn add_with_carry(
+        &mut self,
+        a: &[WireId],
+        b: &[WireId],
+        carry: Option<WireId>,
+    ) -> (Vec<WireId>, WireId) {
+        check_widths(a, b);
+        let mut carry = carry.unwrap_or_else(|| self.constant(false));
+        let mut sum = Vec::with_capacity(a.len());
+        for (&a, &b) in a.iter().zip(b) {
+            // carry' = carry ^ ((a ^ carry) & (b ^ carry)), the majority of the three bits
+            let ac = self.xor(a, carry);
+            let bc = self.xor(b, carry);
+            sum.push(self.xor(ac, b));
+            let t = self.and(ac, bc);
+            carry = self.xor(carry, t);
+        }
+        (sum, carry)
+    }
+
+    /// `a + b` modulo 2^n
+    pub fn add(&mut self, a: &[WireId], b: &[WireId]) -> Vec<WireId> {
+        self.add_with_carry(a, b, None).0
+    }
+
+    /// `a - b` and whether it borrowed, i.e. whether `a < b` as unsigned values
+    pub fn sub_with_borrow(&mut self, a: &[WireId], b: &[WireId]) -> (Vec<WireId>, WireId) {
+        // a + !b + 1 carries out exactly when there's no borrow
+        let not_b = self.not_word(b);
+        let one = self.constant(true);
+        let (diff, carry) = self.add_with_carry(a, &not_b, Some(one));
+        let borrow = self.not(carry);
+        (diff, borrow)
+    }
+
+    /// `a - b` modulo 2^n
+    pub fn sub(&mut self, a: &[WireId], b: &[WireId]) -> Vec<WireId> {
+        self.sub_with_borrow(a, b).0
+    }
+
+    /// `a * b` modulo 2^n, by shift and add
+    pub fn mul(&mut self, a: &[WireId], b: &[WireId]) -> Vec<WireId> {
+        check_widths(a, b);
+        let n = a.len();
+        let mut acc = self.constant_word(0, n);
+        for (i, &bi) in b.iter().enumerate() {
+            // a << i, dropping the bits past the width
+            let row: Vec<WireId> = a[..n - i].iter().map(|&aj| self.and(aj, bi)).collect();
+            let high = if i == 0 {
+                row
+            } else {
+                self.add(&acc[i..], &row)
+            };
+            acc.splice(i.., high);
+        }
+        acc
+    }
+
+    /// Whether `a == b`
+    pub fn eq(&mut self, a: &[WireId], b: &[WireId]) -> WireId {
+        check_widths(a, b);
+        let mut same: Vec<WireId> = a
+            .iter()
+            .zip(b)
+            .map(|(&a, &b)| self.gate(GateType::Xnor, vec![a, b]))
+            .collect();
+        // AND the bits together as a balanced tree
+        while same.len() > 1 {
+            same = same
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [x, y] => self.and(x, y),
+                    [x] => x,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        same.pop().unwrap_or_else(|| self.constant(true))
+    }
+
+    /// Whether `a < b` as unsigned values
+    pub fn lt(&mut self, a: &[WireId], b: &[WireId]) -> WireId {
+        self.sub_with_borrow(a, b).1
+    }
+
+    /// Whether `a <= b` as unsigned values
+    pub fn le(&mut self, a: &[WireId], b: &[WireId]) -> WireId {
+        let gt = self.lt(b, a);
+        self.not(gt)
+    }
+
+    /// Whether `a > b` as unsigned values
+    pub fn gt(&mut self, a: &[WireId], b: &[WireId]) -> WireId {
+        self.lt(b, a)
+    }
+
+    /// Whether `a >= b` as unsigned values
+    pub fn ge(&mut self, a: &[WireId], b: &[WireId]) -> WireId {
+        let lt = self.lt(a, b);
+        self.not(lt)
+    }
+
+    /// Whether `a < b` as two's complement values
+    pub fn lt_signed(&mut self, a: &[WireId], b: &[WireId]) -> WireId {
+        // flipping the sign bits maps signed order to unsigned order
+        let (mut a, mut b) = (a.to_vec(), b.to_vec());
+        if let (Some(sa), Some(sb)) = (a.last_mut(), b.last_mut()) {
+            *sa = self.not(*sa);
+            *sb = self.not(*sb);
+        }
+        self.lt(&a, &b)
+    }
+}
+
+fn check_widths(a: &[WireId], b: &[WireId]) {
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "values of different widths ({} and {} bits)",
+        a.len(),
+        b.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha12Rng;
+
+    const WIDTH: usize = 16;
+
+    fn bits_of(n: u16) -> Vec<bool> {
+        (0..WIDTH).map(|i| (n >> i) & 1 == 1).collect()
+    }
+
+    fn value_of(bits: &[bool]) -> u16 {
+        bits.iter().rev().fold(0, |n, &b| (n << 1) | u16::from(b))
+    }
+
+    #[test]
+    fn gadgets_match_integer_arithmetic() {
+        let mut b = CircuitBuilder::new();
+        let x = b.input(WIDTH);
+        let y = b.input(WIDTH);
+        let sel = b.input(1)[0];
+        let sum = b.add(&x, &y);
+        let diff = b.sub(&x, &y);
+        let prod = b.mul(&x, &y);
+        let picked = b.mux_word(sel, &x, &y);
+        let cmp = [
+            b.eq(&x, &y),
+            b.lt(&x, &y),
+            b.le(&x, &y),
+            b.gt(&x, &y),
+            b.ge(&x, &y),
+            b.lt_signed(&x, &y),
+        ];
+        for value in [&sum, &diff, &prod, &picked, &cmp[..], &x] {
+            b.output(value);
+        }
+        let ckt = b.build().unwrap();
+        assert!(ckt.has_standard_layout());
+
+        let mut rng = ChaCha12Rng::seed_from_u64(40);
+        let mut cases: Vec<(u16, u16)> = (0..200).map(|_| (rng.random(), rng.random())).collect();
+        cases.extend([(0, 0), (7, 7), (0x8000, 1), (1, 0x8000), (0xffff, 0xffff)]);
+        for (i, (x, y)) in cases.into_iter().enumerate() {
+            let sel = i % 2 == 0;
+            let out = ckt.eval(&[bits_of(x), bits_of(y), vec![sel]]).unwrap();
+            assert_eq!(value_of(&out[0]), x.wrapping_add(y));
+            assert_eq!(value_of(&out[1]), x.wrapping_sub(y));
+            assert_eq!(value_of(&out[2]), x.wrapping_mul(y));
+            assert_eq!(value_of(&out[3]), if sel { x } else { y });
+            assert_eq!(
+                out[4],
+                vec![
+                    x == y,
+                    x < y,
+                    x <= y,
+                    x > y,
+                    x >= y,
+                    (x as i16) < (y as i16)
+                ]
+            );
+            assert_eq!(value_of(&out[5]), x);
+        }
+    }
+
+    #[test]
+    fn threshold_predicate() {
+        // does the sum of three 8-bit scores reach 200?
+        let mut b = CircuitBuilder::new();
+        let scores: Vec<Vec<WireId>> = (0..3).map(|_| b.input(8)).collect();
+        let zero = b.constant(false);
+        let mut total = b.constant_word(0, 10);
+        for s in &scores {
+            let wide: Vec<WireId> = s.iter().copied().chain([zero, zero]).collect();
+            total = b.add(&total, &wide);
+        }
+        let threshold = b.constant_word(200, 10);
+        let pass = b.ge(&total, &threshold);
+        b.output(&[pass]);
+        let ckt = b.build().unwrap();
+
+        let byte = |n: u8| (0..8).map(|i| (n >> i) & 1 == 1).collect::<Vec<_>>();
+        for (s, expected) in [
+            ([50, 50, 99], false),
+            ([50, 50, 100], true),
+            ([255; 3], true),
+        ] {
+            let out = ckt.eval(&s.map(byte)).unwrap();
+            assert_eq!(out, vec![vec![expected]]);
+        }
+    }
+}
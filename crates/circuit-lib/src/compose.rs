@@ -0,0 +1,149 @@
+//! Sequential and parallel composition of circuits.
+
+use crate::{Circuit, Gate, WireId};
+
+impl Circuit {
+    /// Feed the outputs of `self` into the first input values of `next`. Input values of `next`
+    /// that are left over become inputs of the result, after those of `self`; the outputs are
+    /// those of `next`. The outputs of `self` must line up with whole input values of `next`.
+    pub fn then(&self, next: &Circuit) -> anyhow::Result<Circuit> {
+        let n = self.outputs.len();
+        anyhow::ensure!(
+            n <= next.inputs.len(),
+            "{n} outputs don't fit into the {} inputs of the next circuit",
+            next.inputs.len()
+        );
+        let mut fed = 0;
+        let mut groups = next.input_groups.iter();
+        while fed < n {
+            fed += groups.next().copied().unwrap_or(n);
+        }
+        anyhow::ensure!(
+            fed == n,
+            "{n} outputs don't line up with input values of {:?} bits",
+            next.input_groups
+        );
+        let rest_groups: Vec<usize> = groups.copied().collect();
+
+        let mut map: Vec<Option<WireId>> = vec![None; next.num_wires];
+        for (&input, &output) in next.inputs.iter().zip(&self.outputs) {
+            map[input] = Some(output);
+        }
+        let mut num_wires = self.num_wires;
+        let mut wire = |w: WireId| {
+            *map[w].get_or_insert_with(|| {
+                num_wires += 1;
+                num_wires - 1
+            })
+        };
+        let rest_inputs: Vec<WireId> = next.inputs[n..].iter().map(|&w| wire(w)).collect();
+        let next_gates: Vec<Gate> = next.gates.iter().map(|g| renamed(g, &mut wire)).collect();
+        let outputs: Vec<WireId> = next.outputs.iter().map(|&w| wire(w)).collect();
+
+        Circuit {
+            num_wires,
+            inputs: [self.inputs.as_slice(), &rest_inputs].concat(),
+            outputs,
+            input_groups: [self.input_groups.as_slice(), &rest_groups].concat(),
+            output_groups: next.output_groups.clone(),
+            gates: [self.gates.as_slice(), &next_gates].concat(),
+        }
+        .with_standard_layout()
+    }
+
+    /// Place `self` and `other` side by side: inputs and outputs are those of `self` followed by
+    /// those of `other`.
+    pub fn parallel(&self, other: &Circuit) -> anyhow::Result<Circuit> {
+        let offset = |w: WireId| w + self.num_wires;
+        Circuit {
+            num_wires: self.num_wires + other.num_wires,
+            inputs: self
+                .inputs
+                .iter()
+                .copied()
+                .chain(other.inputs.iter().map(|&w| offset(w)))
+                .collect(),
+            outputs: self
+                .outputs
+                .iter()
+                .copied()
+                .chain(other.outputs.iter().map(|&w| offset(w)))
+                .collect(),
+            input_groups: [self.input_groups.as_slice(), &other.input_groups].concat(),
+            output_groups: [self.output_groups.as_slice(), &other.output_groups].concat(),
+            gates: self
+                .gates
+                .iter()
+                .cloned()
+                .chain(other.gates.iter().map(|g| renamed(g, offset)))
+                .collect(),
+        }
+        .with_standard_layout()
+    }
+}
+
+fn renamed(g: &Gate, mut wire: impl FnMut(WireId) -> WireId) -> Gate {
+    Gate {
+        gate_type: g.gate_type,
+        inputs: g.inputs.iter().map(|&w| wire(w)).collect(),
+        output: wire(g.output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::CircuitBuilder;
+
+    fn bits_of(n: u8) -> Vec<bool> {
+        (0..8).map(|i| (n >> i) & 1 == 1).collect()
+    }
+
+    /// `x + y` on two 8-bit inputs
+    fn adder() -> Circuit {
+        let mut b = CircuitBuilder::new();
+        let x = b.input(8);
+        let y = b.input(8);
+        let sum = b.add(&x, &y);
+        b.output(&sum);
+        b.build().unwrap()
+    }
+
+    #[test]
+    fn add_then_compare() {
+        let mut b = CircuitBuilder::new();
+        let x = b.input(8);
+        let expected = b.input(8);
+        let eq = b.eq(&x, &expected);
+        b.output(&[eq]);
+        let compare = b.build().unwrap();
+
+        let ckt = adder().then(&compare).unwrap();
+        assert!(ckt.has_standard_layout());
+        assert_eq!(ckt.input_groups, vec![8, 8, 8]);
+        assert_eq!(ckt.output_groups, vec![1]);
+        for (x, y, e) in [(3, 4, 7), (3, 4, 8), (200, 100, 44)] {
+            let out = ckt.eval(&[bits_of(x), bits_of(y), bits_of(e)]).unwrap();
+            assert_eq!(out, vec![vec![x.wrapping_add(y) == e]]);
+        }
+
+        // 12 output bits would fill the adder's first input value and half of the second
+        let mut b = CircuitBuilder::new();
+        let wide = b.input(12);
+        b.output(&wide);
+        let misaligned = b.build().unwrap();
+        assert!(misaligned.then(&adder()).is_err());
+    }
+
+    #[test]
+    fn side_by_side() {
+        let ckt = adder().parallel(&adder()).unwrap();
+        assert!(ckt.has_standard_layout());
+        assert_eq!(ckt.input_groups, vec![8, 8, 8, 8]);
+        assert_eq!(ckt.output_groups, vec![8, 8]);
+        let out = ckt
+            .eval(&[bits_of(1), bits_of(2), bits_of(30), bits_of(40)])
+            .unwrap();
+        assert_eq!(out, vec![bits_of(3), bits_of(70)]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Equivalence checking of two circuits with the same I/O shape.
+//!
+//! Circuits with few inputs are simulated on every input, others on random inputs, 64 inputs
+//! per pass of [`Circuit::eval_packed`]. For a proof on large circuits, [`Circuit::write_miter_cnf`]
+//! writes a miter for an external SAT solver: it is unsatisfiable iff the circuits agree on
+//! every input.
+
+use std::io::{self, BufWriter, Write};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::{Circuit, GateType, WireId};
+
+/// How hard [`Circuit::check_equivalence`] tries
+#[derive(Debug, Clone)]
+pub struct EquivConfig {
+    /// simulate every input if the circuits have at most this many input wires
+    pub exhaustive_limit: usize,
+    /// number of random passes of 64 inputs each otherwise
+    pub random_rounds: usize,
+    /// seed of the random inputs
+    pub seed: u64,
+}
+
+impl Default for EquivConfig {
+    fn default() -> Self {
+        EquivConfig {
+            exhaustive_limit: 20,
+            random_rounds: 1024,
+            seed: 0,
+        }
+    }
+}
+
+/// Outcome of [`Circuit::check_equivalence`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    /// no input tells the circuits apart among the `checked` ones, which are all of them if
+    /// `exhaustive`
+    Equivalent { exhaustive: bool, checked: u64 },
+    /// an input the circuits disagree on, with both outputs, one bit vector per value
+    Counterexample {
+        inputs: Vec<Vec<bool>>,
+        left: Vec<Vec<bool>>,
+        right: Vec<Vec<bool>>,
+    },
+}
+
+/// Word of input bit `i` when lane `l` of pass `pass` holds input `64 * pass + l`
+fn counting_word(i: usize, pass: u64) -> u64 {
+    const LOW: [u64; 6] = [
+        0xAAAA_AAAA_AAAA_AAAA,
+        0xCCCC_CCCC_CCCC_CCCC,
+        0xF0F0_F0F0_F0F0_F0F0,
+        0xFF00_FF00_FF00_FF00,
+        0xFFFF_0000_FFFF_0000,
+        0xFFFF_FFFF_0000_0000,
+    ];
+    match LOW.get(i) {
+        Some(&word) => word,
+        None if (pass >> (i - 6)) & 1 == 1 => !0,
+        None => 0,
+    }
+}
+
+impl Circuit {
+    fn check_same_shape(&self, other: &Circuit) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.inputs.len() == other.inputs.len() && self.outputs.len() == other.outputs.len(),
+            "circuits have {} -> {} and {} -> {} I/O wires",
+            self.inputs.len(),
+            self.outputs.len(),
+            other.inputs.len(),
+            other.outputs.len()
+        );
+        anyhow::ensure!(
+            self.input_groups == other.input_groups && self.output_groups == other.output_groups,
+            "circuits group their I/O differently: {:?} -> {:?} and {:?} -> {:?}",
+            self.input_groups,
+            self.output_groups,
+            other.input_groups,
+            other.output_groups
+        );
+        Ok(())
+    }
+
+    /// Look for an input on which `self` and `other` differ, by simulating both on every input
+    /// or on random ones, after sorting the gates of either into evaluation order if needed.
+    /// Fails if their I/O wires or groups differ.
+    pub fn check_equivalence(
+        &self,
+        other: &Circuit,
+        config: &EquivConfig,
+    ) -> anyhow::Result<Equivalence> {
+        if !self.is_in_evaluation_order() || !other.is_in_evaluation_order() {
+            return self.sorted()?.check_equivalence(&*other.sorted()?, config);
+        }
+        self.check_same_shape(other)?;
+        let n = self.inputs.len();
+        let exhaustive = n <= config.exhaustive_limit.min(63);
+
+        let mut rng = ChaCha12Rng::seed_from_u64(config.seed);
+        let (passes, lanes) = if exhaustive {
+            let total = 1u64 << n;
+            (total.div_ceil(64), total.min(64))
+        } else {
+            (config.random_rounds as u64, 64)
+        };
+        let lane_mask = if lanes == 64 { !0 } else { (1 << lanes) - 1 };
+
+        let mut inputs = vec![0u64; n];
+        for pass in 0..passes {
+            for (i, word) in inputs.iter_mut().enumerate() {
+                *word = if exhaustive {
+                    counting_word(i, pass)
+                } else {
+                    rng.random()
+                };
+            }
+            let diff = self
+                .eval_packed(&inputs)?
+                .into_iter()
+                .zip(other.eval_packed(&inputs)?)
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                & lane_mask;
+            if diff != 0 {
+                let lane = diff.trailing_zeros();
+                let bits: Vec<bool> = inputs.iter().map(|&x| (x >> lane) & 1 == 1).collect();
+                let mut rest = bits.as_slice();
+                let inputs: Vec<Vec<bool>> = self
+                    .input_groups
+                    .iter()
+                    .map(|&width| {
+                        let (value, tail) = rest.split_at(width);
+                        rest = tail;
+                        value.to_vec()
+                    })
+                    .collect();
+                return Ok(Equivalence::Counterexample {
+                    left: self.eval(&inputs)?,
+                    right: other.eval(&inputs)?,
+                    inputs,
+                });
+            }
+        }
+        Ok(Equivalence::Equivalent {
+            exhaustive,
+            checked: if exhaustive { 1 << n } else { passes * 64 },
+        })
+    }
+
+    /// Write a DIMACS CNF miter of `self` and `other`, satisfiable iff some input makes an
+    /// output differ. Input wire `i` of both circuits is variable `i + 1`, so a satisfying
+    /// assignment starts with the counterexample.
+    pub fn write_miter_cnf<W: Write>(&self, other: &Circuit, w: W) -> anyhow::Result<()> {
+        self.check_same_shape(other)?;
+        let n = self.inputs.len() as i64;
+        let left = variables(self, 1..=n, n + 1);
+        let right = variables(other, 1..=n, n + 1 + self.num_wires as i64);
+        let diff_base = n + 1 + (self.num_wires + other.num_wires) as i64;
+        let num_vars = diff_base + self.outputs.len() as i64 - 1;
+
+        let clauses = |emit: &mut dyn FnMut(&[i64]) -> io::Result<()>| -> io::Result<()> {
+            gate_clauses(self, &left, emit)?;
+            gate_clauses(other, &right, emit)?;
+            let mut any_diff = Vec::with_capacity(self.outputs.len());
+            for (i, (&a, &b)) in self.outputs.iter().zip(&other.outputs).enumerate() {
+                let (a, b, d) = (left[a], right[b], diff_base + i as i64);
+                // d <-> a XOR b
+                emit(&[-d, a, b])?;
+                emit(&[-d, -a, -b])?;
+                emit(&[d, -a, b])?;
+                emit(&[d, a, -b])?;
+                any_diff.push(d);
+            }
+            emit(&any_diff)
+        };
+
+        let mut num_clauses = 0u64;
+        clauses(&mut |_| {
+            num_clauses += 1;
+            Ok(())
+        })?;
+
+        let mut w = BufWriter::new(w);
+        writeln!(
+            w,
+            "c miter of two circuits, satisfiable iff they differ; inputs are variables 1..={n}"
+        )?;
+        writeln!(w, "p cnf {num_vars} {num_clauses}")?;
+        clauses(&mut |clause| {
+            for lit in clause {
+                write!(w, "{lit} ")?;
+            }
+            writeln!(w, "0")
+        })?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// One variable per wire: the inputs get `inputs` in order, the other wires count up from
+/// `next`
+fn variables(ckt: &Circuit, inputs: impl Iterator<Item = i64>, next: i64) -> Vec<i64> {
+    let mut var = vec![0; ckt.num_wires];
+    for (&w, v) in ckt.inputs.iter().zip(inputs) {
+        var[w] = v;
+    }
+    for (w, v) in var.iter_mut().enumerate() {
+        if *v == 0 {
+            *v = next + w as i64;
+        }
+    }
+    var
+}
+
+/// Tseitin clauses of every gate, and wires that are neither inputs nor driven fixed to 0 as
+/// in simulation
+fn gate_clauses(
+    ckt: &Circuit,
+    var: &[i64],
+    emit: &mut dyn FnMut(&[i64]) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut driven = vec![false; ckt.num_wires];
+    for &w in &ckt.inputs {
+        driven[w] = true;
+    }
+    for g in &ckt.gates {
+        driven[g.output] = true;
+        let out = var[g.output];
+        let ins: Vec<i64> = g.inputs.iter().map(|&w: &WireId| var[w]).collect();
+        match g.gate_type {
+            GateType::Const(v) => emit(&[if v { out } else { -out }])?,
+            GateType::Buf => {
+                emit(&[-ins[0], out])?;
+                emit(&[ins[0], -out])?;
+            }
+            GateType::Inv => {
+                emit(&[ins[0], out])?;
+                emit(&[-ins[0], -out])?;
+            }
+            gate_type => {
+                let tt = gate_type
+                    .truth_table()
+                    .expect("2-input gates have a truth table");
+                // for each row (a, b): a and b as in the row imply the row's output
+                for row in 0..4 {
+                    let a = if row & 2 != 0 { -ins[0] } else { ins[0] };
+                    let b = if row & 1 != 0 { -ins[1] } else { ins[1] };
+                    let o = if (tt >> row) & 1 == 1 { out } else { -out };
+                    emit(&[a, b, o])?;
+                }
+            }
+        }
+    }
+    for (w, &driven) in driven.iter().enumerate() {
+        if !driven {
+            emit(&[-var[w]])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{circuit, gate};
+    use rand::seq::SliceRandom;
+
+    /// `(a AND b) XOR c`, and the same with `a OR b` when `or` is set
+    fn and_xor(or: bool) -> Circuit {
+        let first = if or { GateType::Or } else { GateType::And };
+        circuit(
+            3,
+            vec![gate(first, &[0, 1], 3), gate(GateType::Xor, &[3, 2], 4)],
+            vec![4],
+        )
+    }
+
+    #[test]
+    fn finds_counterexamples_exhaustively_and_randomly() {
+        let config = EquivConfig::default();
+        let ckt = and_xor(false);
+        let rewritten = ckt.absorb_inverters().with_standard_layout().unwrap();
+        assert_eq!(
+            ckt.check_equivalence(&rewritten, &config).unwrap(),
+            Equivalence::Equivalent {
+                exhaustive: true,
+                checked: 8
+            }
+        );
+
+        let Equivalence::Counterexample {
+            inputs,
+            left,
+            right,
+        } = ckt.check_equivalence(&and_xor(true), &config).unwrap()
+        else {
+            panic!("AND and OR should differ");
+        };
+        assert_ne!(inputs[0][0], inputs[0][1]);
+        assert_ne!(left, right);
+
+        let big = Circuit::random(80, 2000, 0.5, 4);
+        let optimized = big.simplify().unwrap();
+        let result = big.check_equivalence(&optimized, &config).unwrap();
+        assert_eq!(
+            result,
+            Equivalence::Equivalent {
+                exhaustive: false,
+                checked: 64 * 1024
+            }
+        );
+        // an XOR driving an output turned into XNOR inverts that output on every input
+        let mut flipped = big.clone();
+        let g = flipped
+            .gates
+            .iter()
+            .position(|g| g.gate_type == GateType::Xor && big.outputs.contains(&g.output))
+            .expect("an output driven by an XOR");
+        flipped.gates[g].gate_type = GateType::Xnor;
+        let Equivalence::Counterexample {
+            inputs,
+            left,
+            right,
+        } = big.check_equivalence(&flipped, &config).unwrap()
+        else {
+            panic!("XOR and XNOR should differ");
+        };
+        assert_ne!(left, right);
+        assert_eq!(left, big.eval(&inputs).unwrap());
+        assert_eq!(right, flipped.eval(&inputs).unwrap());
+        assert!(ckt.check_equivalence(&big, &config).is_err());
+    }
+
+    #[test]
+    fn sorts_gates_out_of_order_first() {
+        let ckt = Circuit::random(12, 500, 0.5, 3);
+        let mut shuffled = ckt.clone();
+        shuffled.gates.shuffle(&mut ChaCha12Rng::seed_from_u64(2));
+        assert!(!shuffled.is_in_evaluation_order());
+        let sorted = shuffled.reorder().unwrap();
+        let config = EquivConfig::default();
+        for (a, b) in [
+            (&shuffled, &sorted),
+            (&sorted, &shuffled),
+            (&shuffled, &ckt),
+        ] {
+            assert!(matches!(
+                a.check_equivalence(b, &config).unwrap(),
+                Equivalence::Equivalent {
+                    exhaustive: true,
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn miter_has_a_clause_per_gate_row() {
+        let mut cnf = Vec::new();
+        and_xor(false)
+            .write_miter_cnf(&and_xor(true), &mut cnf)
+            .unwrap();
+        let cnf = String::from_utf8(cnf).unwrap();
+        // 3 inputs, a slot per wire of each circuit and one difference variable; 2 x 2 x 4
+        // gate clauses, 4 for the difference and the final clause
+        assert!(cnf.contains("p cnf 14 21\n"), "{cnf}");
+        assert!(cnf.ends_with("\n14 0\n"));
+    }
+}
@@ -0,0 +1,279 @@
+//! Graphviz (DOT) and JSON renderings of a circuit, optionally restricted to part of it.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+
+use crate::{Circuit, GateType, WireId};
+
+/// Which gates to export. The default is the whole circuit.
+#[derive(Debug, Clone, Default)]
+pub struct View {
+    /// only gates at most this many gates away from the inputs
+    pub max_depth: Option<usize>,
+    /// only gates within `radius` gates of these wires, following wires in both directions
+    pub around: Vec<WireId>,
+    pub radius: usize,
+}
+
+impl View {
+    /// Which gates of `ckt` are in view, by gate index
+    pub fn select(&self, ckt: &Circuit) -> Vec<bool> {
+        let mut selected = vec![true; ckt.gates.len()];
+
+        if let Some(max_depth) = self.max_depth {
+            let mut depth = vec![0usize; ckt.num_wires];
+            for (i, g) in ckt.gates.iter().enumerate() {
+                depth[g.output] = 1 + g.inputs.iter().map(|&w| depth[w]).max().unwrap_or(0);
+                selected[i] = depth[g.output] <= max_depth;
+            }
+        }
+
+        if !self.around.is_empty() {
+            // gates touching each wire, as reader or driver
+            let mut touching: Vec<Vec<usize>> = vec![Vec::new(); ckt.num_wires];
+            for (i, g) in ckt.gates.iter().enumerate() {
+                for &w in g.inputs.iter().chain([&g.output]) {
+                    touching[w].push(i);
+                }
+            }
+
+            let mut near = vec![false; ckt.gates.len()];
+            let mut dist: Vec<Option<usize>> = vec![None; ckt.num_wires];
+            let mut queue = VecDeque::new();
+            for &w in self.around.iter().filter(|&&w| w < ckt.num_wires) {
+                dist[w] = Some(0);
+                queue.push_back(w);
+            }
+            while let Some(w) = queue.pop_front() {
+                let d = dist[w].expect("queued wires have a distance");
+                if d == self.radius {
+                    continue;
+                }
+                for &i in &touching[w] {
+                    near[i] = true;
+                    let g = &ckt.gates[i];
+                    for &next in g.inputs.iter().chain([&g.output]) {
+                        if dist[next].is_none() {
+                            dist[next] = Some(d + 1);
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+            for (s, n) in selected.iter_mut().zip(near) {
+                *s &= n;
+            }
+        }
+        selected
+    }
+}
+
+/// Split `wires` into consecutive groups of the given sizes, or one group if they don't fit
+fn grouped(wires: &[WireId], groups: &[usize]) -> Vec<Vec<WireId>> {
+    if groups.iter().sum::<usize>() != wires.len() {
+        return vec![wires.to_vec()];
+    }
+    let mut rest = wires;
+    groups
+        .iter()
+        .map(|&n| {
+            let (group, tail) = rest.split_at(n);
+            rest = tail;
+            group.to_vec()
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct JsonGate {
+    #[serde(rename = "type")]
+    gate_type: String,
+    inputs: Vec<WireId>,
+    output: WireId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct JsonNetlist {
+    num_wires: usize,
+    inputs: Vec<Vec<WireId>>,
+    outputs: Vec<Vec<WireId>>,
+    gates: Vec<JsonGate>,
+}
+
+impl Circuit {
+    /// Write the gates in `view` as a Graphviz digraph. Inputs and outputs are drawn in one
+    /// cluster per I/O group, and wires read from gates out of view are drawn as dashed points.
+    pub fn write_dot<W: Write>(&self, mut w: W, view: &View) -> io::Result<()> {
+        let selected = view.select(self);
+        let mut driver: Vec<Option<usize>> = vec![None; self.num_wires];
+        for (i, g) in self.gates.iter().enumerate() {
+            driver[g.output] = Some(i);
+        }
+        let mut is_input = vec![false; self.num_wires];
+        for &i in &self.inputs {
+            is_input[i] = true;
+        }
+        // node a wire's value comes from
+        let source = |wire: WireId| match driver[wire] {
+            Some(i) if selected[i] => format!("g{i}"),
+            _ if is_input[wire] => format!("in{wire}"),
+            _ => format!("w{wire}"),
+        };
+
+        writeln!(w, "digraph circuit {{")?;
+        writeln!(w, "  rankdir=TB;")?;
+        for (k, group) in grouped(&self.inputs, &self.input_groups).iter().enumerate() {
+            writeln!(w, "  subgraph cluster_input{k} {{")?;
+            writeln!(w, "    label=\"input {k}\"; rank=source;")?;
+            for &i in group {
+                writeln!(w, "    in{i} [shape=circle, label=\"{i}\"];")?;
+            }
+            writeln!(w, "  }}")?;
+        }
+
+        let mut dangling = vec![false; self.num_wires];
+        for (i, g) in self.gates.iter().enumerate().filter(|(i, _)| selected[*i]) {
+            let label = match g.gate_type {
+                GateType::Const(v) => format!("{}", u8::from(v)),
+                t => t.to_string(),
+            };
+            writeln!(w, "  g{i} [shape=box, label=\"{label}\"];")?;
+            for &input in &g.inputs {
+                if !is_input[input] && driver[input].is_none_or(|d| !selected[d]) {
+                    dangling[input] = true;
+                }
+                writeln!(w, "  {} -> g{i} [label=\"{input}\"];", source(input))?;
+            }
+        }
+        for (wire, _) in dangling.iter().enumerate().filter(|(_, d)| **d) {
+            writeln!(
+                w,
+                "  w{wire} [shape=point, style=dashed, xlabel=\"{wire}\"];"
+            )?;
+        }
+
+        for (k, group) in grouped(&self.outputs, &self.output_groups)
+            .iter()
+            .enumerate()
+        {
+            let shown: Vec<WireId> = group
+                .iter()
+                .copied()
+                .filter(|&o| is_input[o] || driver[o].is_some_and(|d| selected[d]))
+                .collect();
+            if shown.is_empty() {
+                continue;
+            }
+            writeln!(w, "  subgraph cluster_output{k} {{")?;
+            writeln!(w, "    label=\"output {k}\"; rank=sink;")?;
+            for &o in &shown {
+                writeln!(w, "    out{o} [shape=doublecircle, label=\"{o}\"];")?;
+            }
+            writeln!(w, "  }}")?;
+            for &o in &shown {
+                writeln!(w, "  {} -> out{o};", source(o))?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Write the gates in `view` as a JSON netlist: the wire count, the input and output wires
+    /// grouped per Bristol value, and the gates with their type, inputs and output.
+    pub fn write_json<W: Write>(&self, w: W, view: &View) -> io::Result<()> {
+        let selected = view.select(self);
+        let netlist = JsonNetlist {
+            num_wires: self.num_wires,
+            inputs: grouped(&self.inputs, &self.input_groups),
+            outputs: grouped(&self.outputs, &self.output_groups),
+            gates: self
+                .gates
+                .iter()
+                .zip(selected)
+                .filter(|(_, s)| *s)
+                .map(|(g, _)| JsonGate {
+                    gate_type: g.gate_type.to_string(),
+                    inputs: g.inputs.clone(),
+                    output: g.output,
+                    value: match g.gate_type {
+                        GateType::Const(v) => Some(v),
+                        _ => None,
+                    },
+                })
+                .collect(),
+        };
+        serde_json::to_writer_pretty(w, &netlist).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::example_path;
+
+    fn example1() -> Circuit {
+        Circuit::from_bristol_fashion(&example_path("example1")).unwrap()
+    }
+
+    #[test]
+    fn dot_of_example1() {
+        let mut out = Vec::new();
+        example1().write_dot(&mut out, &View::default()).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("g0 [shape=box, label=\"AND\"];"));
+        assert!(dot.contains("in0 -> g0 [label=\"0\"];"));
+        assert!(dot.contains("g2 -> g3 [label=\"7\"];"));
+        assert!(dot.contains("g3 -> out8;"));
+        assert!(!dot.contains("shape=point"));
+    }
+
+    #[test]
+    fn views_restrict_the_gates() {
+        let ckt = example1();
+        let shallow = View {
+            max_depth: Some(1),
+            ..View::default()
+        };
+        assert_eq!(shallow.select(&ckt), vec![true, true, false, false]);
+
+        // wire 7 is driven by gate 2 and read by gate 3
+        let around = View {
+            around: vec![7],
+            radius: 1,
+            ..View::default()
+        };
+        assert_eq!(around.select(&ckt), vec![false, false, true, true]);
+        let wider = View {
+            radius: 2,
+            ..around.clone()
+        };
+        assert_eq!(wider.select(&ckt), vec![true, true, true, true]);
+
+        let mut out = Vec::new();
+        ckt.write_dot(&mut out, &around).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("w5 [shape=point"));
+        assert!(dot.contains("w5 -> g2 [label=\"5\"];"));
+        assert!(!dot.contains("g0 ["));
+    }
+
+    #[test]
+    fn json_netlist_of_example1() {
+        let mut out = Vec::new();
+        example1().write_json(&mut out, &View::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["num_wires"], 9);
+        assert_eq!(json["inputs"], serde_json::json!([[0, 1, 2, 3, 4]]));
+        assert_eq!(json["outputs"], serde_json::json!([[8]]));
+        assert_eq!(
+            json["gates"][3],
+            serde_json::json!({"type": "AND", "inputs": [7, 4], "output": 8})
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! BLAKE3 in its default hashing mode, 32-byte output
+
+use super::{Word, const_byte, const_word, finish, input_bytes, output_bytes, rotr, sha256::IV};
+use crate::{Circuit, WireId, builder::CircuitBuilder};
+
+const CHUNK_LEN: usize = 1024;
+const BLOCK_LEN: usize = 64;
+
+const CHUNK_START: u32 = 1;
+const CHUNK_END: u32 = 2;
+const PARENT: u32 = 4;
+const ROOT: u32 = 8;
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+impl Circuit {
+    /// BLAKE3 hash of a `len`-byte message. Messages of more than 1024 bytes are split into
+    /// chunks combined by a tree of parent nodes, as in the reference implementation.
+    pub fn blake3(len: usize) -> anyhow::Result<Circuit> {
+        let mut b = CircuitBuilder::new();
+        let bytes = input_bytes(&mut b, len);
+        let chunks: Vec<&[Word]> = if bytes.is_empty() {
+            vec![&[]]
+        } else {
+            bytes.chunks(CHUNK_LEN).collect()
+        };
+        let cv = tree(&mut b, &chunks, 0, true);
+        let digest: Vec<Word> = cv
+            .iter()
+            .flat_map(|w| w.chunks(8).map(<[_]>::to_vec))
+            .collect();
+        output_bytes(&mut b, &digest);
+        finish(b)
+    }
+}
+
+/// Chaining value of the subtree over `chunks`, the first of which has index `first`
+fn tree(b: &mut CircuitBuilder, chunks: &[&[Word]], first: u64, root: bool) -> Vec<Word> {
+    if let [chunk] = chunks {
+        return chunk_cv(b, chunk, first, root);
+    }
+    // the left subtree takes the largest power of two of chunks that leaves some to the right
+    let left_len = chunks.len().next_power_of_two() / 2;
+    let mut block = tree(b, &chunks[..left_len], first, false);
+    block.extend(tree(b, &chunks[left_len..], first + left_len as u64, false));
+    let key: Vec<Word> = IV.iter().map(|&k| const_word(b, k)).collect();
+    let flags = PARENT | if root { ROOT } else { 0 };
+    compress(b, &key, &block, 0, BLOCK_LEN as u32, flags)
+}
+
+fn chunk_cv(b: &mut CircuitBuilder, chunk: &[Word], index: u64, root: bool) -> Vec<Word> {
+    let mut cv: Vec<Word> = IV.iter().map(|&k| const_word(b, k)).collect();
+    let num_blocks = chunk.len().div_ceil(BLOCK_LEN).max(1);
+    for i in 0..num_blocks {
+        let mut bytes = chunk[(i * BLOCK_LEN).min(chunk.len())..].to_vec();
+        bytes.truncate(BLOCK_LEN);
+        let block_len = bytes.len() as u32;
+        bytes.resize_with(BLOCK_LEN, || const_byte(b, 0));
+        let words: Vec<Word> = bytes.chunks(4).map(<[Word]>::concat).collect();
+
+        let mut flags = 0;
+        if i == 0 {
+            flags |= CHUNK_START;
+        }
+        if i == num_blocks - 1 {
+            flags |= CHUNK_END | if root { ROOT } else { 0 };
+        }
+        cv = compress(b, &cv, &words, index, block_len, flags);
+    }
+    cv
+}
+
+/// The first eight words of the compression function's output
+fn compress(
+    b: &mut CircuitBuilder,
+    cv: &[Word],
+    block: &[Word],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> Vec<Word> {
+    let mut v: Vec<Word> = cv.to_vec();
+    for k in [IV[0], IV[1], IV[2], IV[3]] {
+        v.push(const_word(b, k));
+    }
+    for k in [counter as u32, (counter >> 32) as u32, block_len, flags] {
+        v.push(const_word(b, k));
+    }
+
+    let mut m = block.to_vec();
+    for round in 0..7 {
+        if round > 0 {
+            m = MSG_PERMUTATION.iter().map(|&i| m[i].clone()).collect();
+        }
+        for (i, [a, bb, c, d]) in [
+            [0, 4, 8, 12],
+            [1, 5, 9, 13],
+            [2, 6, 10, 14],
+            [3, 7, 11, 15],
+            [0, 5, 10, 15],
+            [1, 6, 11, 12],
+            [2, 7, 8, 13],
+            [3, 4, 9, 14],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            g(b, &mut v, [a, bb, c, d], &m[2 * i], &m[2 * i + 1]);
+        }
+    }
+    (0..8).map(|i| b.xor_word(&v[i], &v[i + 8])).collect()
+}
+
+/// The quarter-round mixing function
+fn g(
+    b: &mut CircuitBuilder,
+    v: &mut [Word],
+    [a, bb, c, d]: [usize; 4],
+    x: &[WireId],
+    y: &[WireId],
+) {
+    for (msg, r1, r2) in [(x, 16, 12), (y, 8, 7)] {
+        let sum = b.add(&v[a], &v[bb]);
+        v[a] = b.add(&sum, msg);
+        let dx = b.xor_word(&v[d], &v[a]);
+        v[d] = rotr(&dx, r1);
+        v[c] = b.add(&v[c], &v[d]);
+        let bx = b.xor_word(&v[bb], &v[c]);
+        v[bb] = rotr(&bx, r2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{bits_to_bytes, bytes_to_bits};
+
+    #[test]
+    fn matches_blake3() {
+        // one chunk of one or more blocks, and a tree of two chunks
+        for len in [0, 1, 64, 65, 200, 1025] {
+            let msg: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let ckt = Circuit::blake3(len).unwrap();
+            let out = ckt.eval_bits(&bytes_to_bits(&msg)).unwrap();
+            assert_eq!(
+                bits_to_bytes(&out),
+                ::blake3::hash(&msg).as_bytes().to_vec(),
+                "{len} bytes"
+            );
+        }
+    }
+}
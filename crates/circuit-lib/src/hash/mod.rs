@@ -0,0 +1,74 @@
+//! Generators for hash function circuits, built from AND, XOR and INV gates.
+//!
+//! Messages and digests are byte strings read as big-endian numbers: the first wire is the least
+//! significant bit of the last byte. That way `circuit-utils eval` reads a message written in
+//! hex and prints the digest in the usual hex encoding. [`bytes_to_bits`] and
+//! [`bits_to_bytes`] convert between byte strings and wire values.
+
+use crate::{Circuit, WireId, builder::CircuitBuilder};
+
+mod blake3;
+mod sha256;
+
+/// A 32-bit word or a byte, least significant bit first
+type Word = Vec<WireId>;
+
+/// Wire values of a byte string
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .rev()
+        .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .collect()
+}
+
+/// Byte string of wire values, the inverse of [`bytes_to_bits`]
+pub fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .rev()
+        .map(|byte| byte.iter().rev().fold(0, |n, &b| (n << 1) | u8::from(b)))
+        .collect()
+}
+
+/// Allocate an input of `len` bytes and return them in message order
+fn input_bytes(b: &mut CircuitBuilder, len: usize) -> Vec<Word> {
+    b.input(8 * len)
+        .chunks(8)
+        .rev()
+        .map(<[_]>::to_vec)
+        .collect()
+}
+
+fn output_bytes(b: &mut CircuitBuilder, bytes: &[Word]) {
+    let wires: Vec<WireId> = bytes.iter().rev().flatten().copied().collect();
+    b.output(&wires);
+}
+
+fn const_byte(b: &mut CircuitBuilder, value: u8) -> Word {
+    b.constant_word(value.into(), 8)
+}
+
+fn const_word(b: &mut CircuitBuilder, value: u32) -> Word {
+    b.constant_word(value.into(), 32)
+}
+
+fn rotr(x: &[WireId], n: usize) -> Word {
+    (0..x.len()).map(|i| x[(i + n) % x.len()]).collect()
+}
+
+/// Build the circuit and fold the constants away, leaving AND, XOR and INV gates
+fn finish(b: CircuitBuilder) -> anyhow::Result<Circuit> {
+    b.build()?.simplify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_strings_are_big_endian_numbers() {
+        let bits = bytes_to_bits(&[0x12, 0x34]);
+        assert_eq!(crate::sim::ValueFormat::Hex.format(&bits), "0x1234");
+        assert_eq!(bits_to_bytes(&bits), vec![0x12, 0x34]);
+    }
+}
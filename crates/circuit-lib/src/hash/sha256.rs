@@ -0,0 +1,174 @@
+//! SHA-256 (FIPS 180-4)
+
+use super::{Word, const_byte, const_word, finish, input_bytes, output_bytes, rotr};
+use crate::{Circuit, WireId, builder::CircuitBuilder};
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Initial hash value, also BLAKE3's IV
+pub(super) const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+impl Circuit {
+    /// SHA-256 of a `len`-byte message. The padding is part of the circuit, which compresses
+    /// `(len + 9).div_ceil(64)` blocks.
+    pub fn sha256(len: usize) -> anyhow::Result<Circuit> {
+        let mut b = CircuitBuilder::new();
+        let mut bytes = input_bytes(&mut b, len);
+        bytes.push(const_byte(&mut b, 0x80));
+        while bytes.len() % 64 != 56 {
+            bytes.push(const_byte(&mut b, 0));
+        }
+        for byte in (8 * len as u64).to_be_bytes() {
+            bytes.push(const_byte(&mut b, byte));
+        }
+        let digest = hash_blocks(&mut b, &bytes);
+        output_bytes(&mut b, &digest);
+        finish(b)
+    }
+
+    /// SHA-256 compression of `num_blocks` already padded 64-byte blocks, starting from the
+    /// initial hash value. The output is the chaining value after the last block, i.e. the
+    /// digest if the blocks are a padded message.
+    pub fn sha256_blocks(num_blocks: usize) -> anyhow::Result<Circuit> {
+        let mut b = CircuitBuilder::new();
+        let bytes = input_bytes(&mut b, 64 * num_blocks);
+        let digest = hash_blocks(&mut b, &bytes);
+        output_bytes(&mut b, &digest);
+        finish(b)
+    }
+}
+
+/// Chaining value after compressing the padded message `bytes`, as bytes
+fn hash_blocks(b: &mut CircuitBuilder, bytes: &[Word]) -> Vec<Word> {
+    let mut state: Vec<Word> = IV.iter().map(|&h| const_word(b, h)).collect();
+    for block in bytes.chunks(64) {
+        state = compress(b, &state, block);
+    }
+    state
+        .iter()
+        .flat_map(|word| word.chunks(8).rev().map(<[_]>::to_vec))
+        .collect()
+}
+
+/// `x >> n`
+fn shr(b: &mut CircuitBuilder, x: &[WireId], n: usize) -> Word {
+    let zero = b.constant(false);
+    (0..x.len())
+        .map(|i| x.get(i + n).copied().unwrap_or(zero))
+        .collect()
+}
+
+fn xor3(b: &mut CircuitBuilder, x: &[WireId], y: &[WireId], z: &[WireId]) -> Word {
+    let xy = b.xor_word(x, y);
+    b.xor_word(&xy, z)
+}
+
+fn compress(b: &mut CircuitBuilder, state: &[Word], block: &[Word]) -> Vec<Word> {
+    let mut w: Vec<Word> = block
+        .chunks(4)
+        .map(|be| [&be[3][..], &be[2], &be[1], &be[0]].concat())
+        .collect();
+    for t in 16..64 {
+        let shr3 = shr(b, &w[t - 15], 3);
+        let s0 = xor3(b, &rotr(&w[t - 15], 7), &rotr(&w[t - 15], 18), &shr3);
+        let shr10 = shr(b, &w[t - 2], 10);
+        let s1 = xor3(b, &rotr(&w[t - 2], 17), &rotr(&w[t - 2], 19), &shr10);
+        let sum = b.add(&w[t - 16], &s0);
+        let sum = b.add(&sum, &w[t - 7]);
+        let wt = b.add(&sum, &s1);
+        w.push(wt);
+    }
+
+    let mut v = state.to_vec();
+    for (t, wt) in w.iter().enumerate() {
+        let [a, bb, c, d, e, f, g, h] = [0, 1, 2, 3, 4, 5, 6, 7].map(|i| &v[i]);
+        let s1 = xor3(b, &rotr(e, 6), &rotr(e, 11), &rotr(e, 25));
+        // Ch(e, f, g) = g ^ (e & (f ^ g))
+        let ch: Word = (0..32).map(|i| b.mux(e[i], f[i], g[i])).collect();
+        let k = const_word(b, K[t]);
+        let t1 = b.add(h, &s1);
+        let t1 = b.add(&t1, &ch);
+        let t1 = b.add(&t1, &k);
+        let t1 = b.add(&t1, wt);
+        let s0 = xor3(b, &rotr(a, 2), &rotr(a, 13), &rotr(a, 22));
+        // Maj(a, b, c) = b ^ ((a ^ b) & (b ^ c))
+        let maj = {
+            let ab = b.xor_word(a, bb);
+            let bc = b.xor_word(bb, c);
+            let both = b.and_word(&ab, &bc);
+            b.xor_word(bb, &both)
+        };
+        let t2 = b.add(&s0, &maj);
+        let new_e = b.add(d, &t1);
+        let new_a = b.add(&t1, &t2);
+        v = vec![
+            new_a,
+            a.clone(),
+            bb.clone(),
+            c.clone(),
+            new_e,
+            e.clone(),
+            f.clone(),
+            g.clone(),
+        ];
+    }
+    state.iter().zip(&v).map(|(h, x)| b.add(h, x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GateType,
+        hash::{bits_to_bytes, bytes_to_bits},
+    };
+    use sha2::{Digest, Sha256};
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 37 + 11) as u8).collect()
+    }
+
+    #[test]
+    fn matches_sha2() {
+        for len in [0, 3, 55, 56, 64, 100] {
+            let ckt = Circuit::sha256(len).unwrap();
+            // the digest of the empty message is a constant
+            assert!(
+                len == 0
+                    || ckt.gates.iter().all(|g| matches!(
+                        g.gate_type,
+                        GateType::And | GateType::Xor | GateType::Inv
+                    ))
+            );
+            let msg = message(len);
+            let out = ckt.eval_bits(&bytes_to_bits(&msg)).unwrap();
+            assert_eq!(
+                bits_to_bytes(&out),
+                Sha256::digest(&msg).to_vec(),
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn blocks_chain_from_the_initial_value() {
+        // "abc" padded to one block
+        let mut block = vec![0u8; 64];
+        block[..4].copy_from_slice(b"abc\x80");
+        block[63] = 24;
+        let ckt = Circuit::sha256_blocks(1).unwrap();
+        let out = ckt.eval_bits(&bytes_to_bits(&block)).unwrap();
+        assert_eq!(bits_to_bytes(&out), Sha256::digest(b"abc").to_vec());
+    }
+}
@@ -0,0 +1,245 @@
+//! BLIF netlists of `.names` covers and Yosys simple cells (`.subckt $_AND_ A=a B=b Y=y`), as
+//! written by Yosys' `write_blif` or ABC.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::Context;
+
+use super::{Cell, CellKind, Net, Netlist, Signal, SimpleCell};
+use crate::Circuit;
+
+impl Circuit {
+    /// Import the first model of a BLIF file. Bits named `a[0]`, `a[1]`, ... are grouped into
+    /// one input or output value per bus, other inputs and outputs are 1-bit values.
+    pub fn from_blif(path: &Path) -> anyhow::Result<Self> {
+        let blif = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        parse_blif(&blif)
+    }
+}
+
+/// `a[3]` is bit 3 of bus `a`
+fn bus_name(name: &str) -> &str {
+    match name.strip_suffix(']').and_then(|s| s.rsplit_once('[')) {
+        Some((bus, index)) if index.parse::<usize>().is_ok() => bus,
+        _ => name,
+    }
+}
+
+/// Split consecutive bits of the same bus into one value each
+fn group_ports<T>(names: &[&str], mut item: impl FnMut(&str) -> T) -> Vec<(String, Vec<T>)> {
+    let mut ports: Vec<(String, Vec<T>)> = Vec::new();
+    for &name in names {
+        let bus = bus_name(name);
+        match ports.last_mut() {
+            Some((last, bits)) if last == bus && bus != name => bits.push(item(name)),
+            _ => ports.push((bus.to_string(), vec![item(name)])),
+        }
+    }
+    ports
+}
+
+fn parse_blif(blif: &str) -> anyhow::Result<Circuit> {
+    // join continued lines and drop comments
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut continued = false;
+    for (i, line) in blif.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let (line, next_continues) = match line.trim_end().strip_suffix('\\') {
+            Some(l) => (l, true),
+            None => (line, false),
+        };
+        match lines.last_mut() {
+            Some((_, last)) if continued => {
+                last.push(' ');
+                last.push_str(line);
+            }
+            _ => lines.push((i + 1, line.to_string())),
+        }
+        continued = next_continues;
+    }
+
+    let mut netlist = Netlist::default();
+    let mut nets: HashMap<String, Net> = HashMap::new();
+    let mut net = |netlist: &mut Netlist, name: &str| -> Net {
+        *nets.entry(name.to_string()).or_insert_with(|| {
+            netlist.net_names.push(name.to_string());
+            netlist.net_names.len() - 1
+        })
+    };
+    let mut input_names: Vec<String> = Vec::new();
+    let mut output_names: Vec<String> = Vec::new();
+    let mut models = 0;
+
+    let mut lines = lines.into_iter().peekable();
+    while let Some((line_no, line)) = lines.next() {
+        let mut tokens = line.split_whitespace();
+        let Some(directive) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+        let context = || format!("line {line_no}: `{}`", line.trim());
+        match directive {
+            ".model" => {
+                models += 1;
+                if models > 1 {
+                    break;
+                }
+            }
+            ".inputs" => input_names.extend(args.iter().map(|s| s.to_string())),
+            ".outputs" => output_names.extend(args.iter().map(|s| s.to_string())),
+            ".names" => {
+                let (&out, ins) = args.split_last().with_context(context)?;
+                let mut cubes = Vec::new();
+                let mut on = None;
+                while let Some((cube_line, cube)) =
+                    lines.next_if(|(_, l)| !l.trim_start().starts_with('.'))
+                {
+                    let fields: Vec<&str> = cube.split_whitespace().collect();
+                    let (pattern, value) = match fields.as_slice() {
+                        [] => continue,
+                        [value] if ins.is_empty() => ("", *value),
+                        [pattern, value] => (*pattern, *value),
+                        _ => {
+                            anyhow::bail!("line {cube_line}: invalid cover line `{}`", cube.trim())
+                        }
+                    };
+                    anyhow::ensure!(
+                        pattern.len() == ins.len() && (value == "0" || value == "1"),
+                        "line {cube_line}: invalid cover line `{}`",
+                        cube.trim()
+                    );
+                    let value = value == "1";
+                    anyhow::ensure!(
+                        *on.get_or_insert(value) == value,
+                        "line {cube_line}: cover mixes on-set and off-set lines"
+                    );
+                    let cube = pattern
+                        .chars()
+                        .map(|c| match c {
+                            '0' => Ok(Some(false)),
+                            '1' => Ok(Some(true)),
+                            '-' => Ok(None),
+                            _ => anyhow::bail!("line {cube_line}: invalid literal `{c}`"),
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    cubes.push(cube);
+                }
+                let inputs = ins
+                    .iter()
+                    .map(|name| Signal::Net(net(&mut netlist, name)))
+                    .collect();
+                let output = net(&mut netlist, out);
+                netlist.cells.push(Cell {
+                    kind: CellKind::Cover {
+                        cubes,
+                        on: on.unwrap_or(true),
+                    },
+                    inputs,
+                    output,
+                });
+            }
+            ".subckt" | ".gate" => {
+                let (&cell_type, connections) = args.split_first().with_context(context)?;
+                let (kind, ports) = SimpleCell::parse(cell_type).with_context(context)?;
+                let connections: HashMap<&str, &str> = connections
+                    .iter()
+                    .map(|c| c.split_once('=').with_context(context))
+                    .collect::<anyhow::Result<_>>()?;
+                let mut port_net = |port: &str| -> anyhow::Result<Net> {
+                    let name = connections
+                        .get(port)
+                        .with_context(|| format!("{}: no `{port}` connection", context()))?;
+                    Ok(net(&mut netlist, name))
+                };
+                let inputs = ports
+                    .iter()
+                    .map(|port| port_net(port).map(Signal::Net))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let output = port_net("Y")?;
+                netlist.cells.push(Cell {
+                    kind: CellKind::Simple(kind),
+                    inputs,
+                    output,
+                });
+            }
+            ".end" => break,
+            // Yosys annotations
+            ".attr" | ".param" | ".cname" => {}
+            d if d.starts_with('.') => anyhow::bail!("{}: unsupported directive", context()),
+            _ => anyhow::bail!("{}: expected a directive", context()),
+        }
+    }
+
+    let inputs: Vec<&str> = input_names.iter().map(String::as_str).collect();
+    netlist.inputs = group_ports(&inputs, |name| net(&mut netlist, name));
+    let outputs: Vec<&str> = output_names.iter().map(String::as_str).collect();
+    netlist.outputs = group_ports(&outputs, |name| Signal::Net(net(&mut netlist, name)));
+    netlist.to_circuit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a 2-bit adder `s = a + b` and a carry out, as ABC writes it
+    const ADDER: &str = "\
+# 2-bit adder
+.model add2
+.inputs a[0] a[1] b[0] \\
+  b[1]
+.outputs s[0] s[1] c
+.names a[0] b[0] s[0]
+10 1
+01 1
+.names a[0] b[0] c0
+11 1
+.subckt $_XOR_ A=a[1] B=b[1] Y=x1
+.subckt $_XOR_ A=x1 B=c0 Y=s[1]
+.names a[1] b[1] c0 c
+11- 1
+1-1 1
+-11 1
+.end
+";
+
+    #[test]
+    fn imports_covers_and_cells() {
+        let ckt = parse_blif(ADDER).unwrap();
+        assert_eq!(ckt.input_groups, vec![2, 2]);
+        assert_eq!(ckt.output_groups, vec![2, 1]);
+        assert!(ckt.gates.iter().all(|g| matches!(
+            g.gate_type,
+            crate::GateType::And | crate::GateType::Xor | crate::GateType::Inv
+        )));
+        for a in 0..4usize {
+            for b in 0..4usize {
+                let bits = |n: usize| vec![n & 1 == 1, n & 2 == 2];
+                let sum = a + b;
+                let out = ckt.eval(&[bits(a), bits(b)]).unwrap();
+                assert_eq!(out, vec![bits(sum), vec![sum >= 4]]);
+            }
+        }
+    }
+
+    #[test]
+    fn off_set_and_constant_covers() {
+        let blif = "\
+.model m
+.inputs x y
+.outputs nand one zero
+.names x y nand
+11 0
+.names one
+1
+.names zero
+.end
+";
+        let ckt = parse_blif(blif).unwrap();
+        for (x, y) in [(false, false), (false, true), (true, false), (true, true)] {
+            let out = ckt.eval_bits(&[x, y]).unwrap();
+            assert_eq!(out, vec![!(x && y), true, false]);
+        }
+        assert!(parse_blif(".model m\n.latch a b 0\n.end\n").is_err());
+    }
+}
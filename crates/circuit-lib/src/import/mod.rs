@@ -0,0 +1,341 @@
+//! Import of gate-level netlists synthesized by Yosys, as `write_json` or BLIF output.
+//!
+//! Both formats are read into a [`Netlist`] of Yosys' simple cells (`$_AND_`, `$_OR_`,
+//! `$_MUX_`, ...) and BLIF covers, which is then lowered to AND, XOR and INV gates. Only
+//! combinational logic is supported; flip-flops and other cells are rejected.
+
+use std::collections::HashMap;
+
+use crate::{Circuit, WireId, builder::CircuitBuilder};
+
+mod blif;
+mod yosys;
+
+/// A net, by index into [`Netlist::net_names`]
+type Net = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Signal {
+    Net(Net),
+    Const(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SimpleCell {
+    Buf,
+    Not,
+    And,
+    Nand,
+    Or,
+    Nor,
+    Xor,
+    Xnor,
+    AndNot,
+    OrNot,
+    Mux,
+    NMux,
+    Aoi3,
+    Oai3,
+    Aoi4,
+    Oai4,
+}
+
+impl SimpleCell {
+    /// The cell type with its name in Yosys (without `$_` and `_`) and its input ports
+    const ALL: [(SimpleCell, &'static str, &'static [&'static str]); 16] = [
+        (SimpleCell::Buf, "BUF", &["A"]),
+        (SimpleCell::Not, "NOT", &["A"]),
+        (SimpleCell::And, "AND", &["A", "B"]),
+        (SimpleCell::Nand, "NAND", &["A", "B"]),
+        (SimpleCell::Or, "OR", &["A", "B"]),
+        (SimpleCell::Nor, "NOR", &["A", "B"]),
+        (SimpleCell::Xor, "XOR", &["A", "B"]),
+        (SimpleCell::Xnor, "XNOR", &["A", "B"]),
+        (SimpleCell::AndNot, "ANDNOT", &["A", "B"]),
+        (SimpleCell::OrNot, "ORNOT", &["A", "B"]),
+        (SimpleCell::Mux, "MUX", &["A", "B", "S"]),
+        (SimpleCell::NMux, "NMUX", &["A", "B", "S"]),
+        (SimpleCell::Aoi3, "AOI3", &["A", "B", "C"]),
+        (SimpleCell::Oai3, "OAI3", &["A", "B", "C"]),
+        (SimpleCell::Aoi4, "AOI4", &["A", "B", "C", "D"]),
+        (SimpleCell::Oai4, "OAI4", &["A", "B", "C", "D"]),
+    ];
+
+    /// Look up a cell type like `$_AND_` and return it with its input ports
+    fn parse(name: &str) -> anyhow::Result<(SimpleCell, &'static [&'static str])> {
+        let short = name
+            .strip_prefix("$_")
+            .and_then(|s| s.strip_suffix('_'))
+            .unwrap_or(name);
+        SimpleCell::ALL
+            .iter()
+            .find(|(_, n, _)| *n == short)
+            .map(|&(cell, _, ports)| (cell, ports))
+            .ok_or_else(|| anyhow::anyhow!("unsupported cell type `{name}`"))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CellKind {
+    Simple(SimpleCell),
+    /// a BLIF cover: the output is `on` for inputs matching one of the cubes (`None` matches
+    /// either value), `!on` otherwise
+    Cover {
+        cubes: Vec<Vec<Option<bool>>>,
+        on: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Cell {
+    kind: CellKind,
+    inputs: Vec<Signal>,
+    output: Net,
+}
+
+/// A combinational netlist with named, multi-bit ports
+#[derive(Debug, Default)]
+struct Netlist {
+    net_names: Vec<String>,
+    inputs: Vec<(String, Vec<Net>)>,
+    outputs: Vec<(String, Vec<Signal>)>,
+    cells: Vec<Cell>,
+}
+
+impl Netlist {
+    /// Order the cells so that each comes after the drivers of its inputs
+    fn sorted_cells(&self) -> anyhow::Result<Vec<&Cell>> {
+        #[derive(Clone, Copy)]
+        enum Driver {
+            Input,
+            Cell(usize),
+        }
+        let mut driver: Vec<Option<Driver>> = vec![None; self.net_names.len()];
+        for (_, nets) in &self.inputs {
+            for &n in nets {
+                driver[n] = Some(Driver::Input);
+            }
+        }
+        for (i, c) in self.cells.iter().enumerate() {
+            anyhow::ensure!(
+                driver[c.output].is_none(),
+                "net `{}` is driven more than once",
+                self.net_names[c.output]
+            );
+            driver[c.output] = Some(Driver::Cell(i));
+        }
+
+        let mut pending = vec![0usize; self.cells.len()];
+        let mut readers: Vec<Vec<usize>> = vec![Vec::new(); self.cells.len()];
+        for (i, c) in self.cells.iter().enumerate() {
+            for s in &c.inputs {
+                if let Signal::Net(n) = *s {
+                    match driver[n] {
+                        None => anyhow::bail!("net `{}` is not driven", self.net_names[n]),
+                        Some(Driver::Input) => {}
+                        Some(Driver::Cell(d)) => {
+                            pending[i] += 1;
+                            readers[d].push(i);
+                        }
+                    }
+                }
+            }
+        }
+        for (name, signals) in &self.outputs {
+            for s in signals {
+                if let Signal::Net(n) = *s {
+                    anyhow::ensure!(
+                        driver[n].is_some(),
+                        "output `{name}` reads net `{}`, which is not driven",
+                        self.net_names[n]
+                    );
+                }
+            }
+        }
+
+        let mut ready: Vec<usize> = (0..self.cells.len()).filter(|&i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.cells.len());
+        while let Some(i) = ready.pop() {
+            order.push(&self.cells[i]);
+            for &r in &readers[i] {
+                pending[r] -= 1;
+                if pending[r] == 0 {
+                    ready.push(r);
+                }
+            }
+        }
+        anyhow::ensure!(
+            order.len() == self.cells.len(),
+            "the netlist has a combinational loop"
+        );
+        Ok(order)
+    }
+
+    /// Lower to AND, XOR and INV gates, one input and output value per port
+    fn to_circuit(&self) -> anyhow::Result<Circuit> {
+        let cells = self.sorted_cells()?;
+        let mut b = CircuitBuilder::new();
+        let mut wires: HashMap<Net, WireId> = HashMap::new();
+        for (_, nets) in &self.inputs {
+            for (&n, w) in nets.iter().zip(b.input(nets.len())) {
+                wires.insert(n, w);
+            }
+        }
+        let wire = |b: &mut CircuitBuilder, wires: &HashMap<Net, WireId>, s: Signal| match s {
+            Signal::Net(n) => wires[&n],
+            Signal::Const(v) => b.constant(v),
+        };
+        for c in cells {
+            let inputs: Vec<WireId> = c.inputs.iter().map(|&s| wire(&mut b, &wires, s)).collect();
+            let out = match &c.kind {
+                CellKind::Simple(cell) => lower_simple(&mut b, *cell, &inputs),
+                CellKind::Cover { cubes, on } => lower_cover(&mut b, cubes, *on, &inputs),
+            };
+            wires.insert(c.output, out);
+        }
+        for (_, signals) in &self.outputs {
+            let outs: Vec<WireId> = signals.iter().map(|&s| wire(&mut b, &wires, s)).collect();
+            b.output(&outs);
+        }
+        // folds the constants and the INV pairs the lowering leaves behind
+        b.build()?.simplify()
+    }
+}
+
+/// `a OR b` as `a ^ b ^ (a & b)`
+fn or(b: &mut CircuitBuilder, x: WireId, y: WireId) -> WireId {
+    let xy = b.xor(x, y);
+    let both = b.and(x, y);
+    b.xor(xy, both)
+}
+
+fn lower_simple(b: &mut CircuitBuilder, cell: SimpleCell, inputs: &[WireId]) -> WireId {
+    let input = |i: usize| inputs[i];
+    match cell {
+        SimpleCell::Buf => input(0),
+        SimpleCell::Not => b.not(input(0)),
+        SimpleCell::And => b.and(input(0), input(1)),
+        SimpleCell::Nand => {
+            let and = b.and(input(0), input(1));
+            b.not(and)
+        }
+        SimpleCell::Or => or(b, input(0), input(1)),
+        SimpleCell::Nor => {
+            let or = or(b, input(0), input(1));
+            b.not(or)
+        }
+        SimpleCell::Xor => b.xor(input(0), input(1)),
+        SimpleCell::Xnor => {
+            let xor = b.xor(input(0), input(1));
+            b.not(xor)
+        }
+        SimpleCell::AndNot => {
+            let not_b = b.not(input(1));
+            b.and(input(0), not_b)
+        }
+        SimpleCell::OrNot => {
+            let not_b = b.not(input(1));
+            or(b, input(0), not_b)
+        }
+        // Y = S ? B : A
+        SimpleCell::Mux => b.mux(input(2), input(1), input(0)),
+        SimpleCell::NMux => {
+            let mux = b.mux(input(2), input(1), input(0));
+            b.not(mux)
+        }
+        SimpleCell::Aoi3 | SimpleCell::Oai3 => {
+            let inner = if cell == SimpleCell::Aoi3 {
+                b.and(input(0), input(1))
+            } else {
+                or(b, input(0), input(1))
+            };
+            let outer = if cell == SimpleCell::Aoi3 {
+                or(b, inner, input(2))
+            } else {
+                b.and(inner, input(2))
+            };
+            b.not(outer)
+        }
+        SimpleCell::Aoi4 => {
+            let ab = b.and(input(0), input(1));
+            let cd = b.and(input(2), input(3));
+            let outer = or(b, ab, cd);
+            b.not(outer)
+        }
+        SimpleCell::Oai4 => {
+            let ab = or(b, input(0), input(1));
+            let cd = or(b, input(2), input(3));
+            let outer = b.and(ab, cd);
+            b.not(outer)
+        }
+    }
+}
+
+/// Sum of products, negated for an off-set cover
+fn lower_cover(
+    b: &mut CircuitBuilder,
+    cubes: &[Vec<Option<bool>>],
+    on: bool,
+    inputs: &[WireId],
+) -> WireId {
+    let mut sum = b.constant(false);
+    for cube in cubes {
+        let mut product = b.constant(true);
+        for (&w, &lit) in inputs.iter().zip(cube) {
+            let literal = match lit {
+                Some(true) => w,
+                Some(false) => b.not(w),
+                None => continue,
+            };
+            product = b.and(product, literal);
+        }
+        sum = or(b, sum, product);
+    }
+    if on { sum } else { b.not(sum) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_cells_match_their_definitions() {
+        for &(cell, name, ports) in &SimpleCell::ALL {
+            let mut b = CircuitBuilder::new();
+            let inputs: Vec<WireId> = ports.iter().map(|_| b.input(1)[0]).collect();
+            let out = lower_simple(&mut b, cell, &inputs);
+            b.output(&[out]);
+            let ckt = b.build().unwrap();
+
+            for row in 0..1usize << ports.len() {
+                let bits: Vec<bool> = (0..ports.len()).map(|i| (row >> i) & 1 == 1).collect();
+                let [a, bb, c, d] = [0, 1, 2, 3].map(|i| bits.get(i).copied().unwrap_or(false));
+                let expected = match cell {
+                    SimpleCell::Buf => a,
+                    SimpleCell::Not => !a,
+                    SimpleCell::And => a & bb,
+                    SimpleCell::Nand => !(a & bb),
+                    SimpleCell::Or => a | bb,
+                    SimpleCell::Nor => !(a | bb),
+                    SimpleCell::Xor => a ^ bb,
+                    SimpleCell::Xnor => !(a ^ bb),
+                    SimpleCell::AndNot => a & !bb,
+                    SimpleCell::OrNot => a | !bb,
+                    SimpleCell::Mux => {
+                        if c {
+                            bb
+                        } else {
+                            a
+                        }
+                    }
+                    SimpleCell::NMux => !(if c { bb } else { a }),
+                    SimpleCell::Aoi3 => !((a & bb) | c),
+                    SimpleCell::Oai3 => !((a | bb) & c),
+                    SimpleCell::Aoi4 => !((a & bb) | (c & d)),
+                    SimpleCell::Oai4 => !((a | bb) & (c | d)),
+                };
+                assert_eq!(ckt.eval_bits(&bits).unwrap(), vec![expected], "{name}");
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Yosys `write_json` netlists, after `synth; abc -g AND,OR,XOR,MUX` or any other mapping to
+//! simple cells.
+
+use std::{collections::HashMap, fmt, fs, marker::PhantomData, path::Path};
+
+use anyhow::Context;
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor},
+};
+
+use super::{Cell, CellKind, Net, Netlist, Signal, SimpleCell};
+use crate::Circuit;
+
+/// A JSON object read in file order, which for ports is the order of the values
+#[derive(Debug)]
+struct Ordered<T>(Vec<(String, T)>);
+
+impl<T> Default for Ordered<T> {
+    fn default() -> Self {
+        Ordered(Vec::new())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Ordered<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for OrderedVisitor<T> {
+            type Value = Ordered<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Ordered(entries))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedVisitor(PhantomData))
+    }
+}
+
+#[derive(Deserialize)]
+struct Design {
+    modules: Ordered<Module>,
+}
+
+#[derive(Deserialize)]
+struct Module {
+    #[serde(default)]
+    attributes: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    ports: Ordered<Port>,
+    #[serde(default)]
+    cells: Ordered<JsonCell>,
+}
+
+#[derive(Deserialize)]
+struct Port {
+    direction: String,
+    bits: Vec<Bit>,
+}
+
+/// A net number, or a constant `"0"`, `"1"`, `"x"` or `"z"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Bit {
+    Net(u64),
+    Const(String),
+}
+
+#[derive(Deserialize)]
+struct JsonCell {
+    #[serde(rename = "type")]
+    cell_type: String,
+    connections: HashMap<String, Vec<Bit>>,
+}
+
+impl Circuit {
+    /// Import a module of a Yosys JSON netlist, with one input and output value per port. The
+    /// module is `top` if given, otherwise the one marked as top or the only one.
+    pub fn from_yosys_json(path: &Path, top: Option<&str>) -> anyhow::Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        parse_yosys_json(&json, top)
+    }
+}
+
+fn parse_yosys_json(json: &str, top: Option<&str>) -> anyhow::Result<Circuit> {
+    let design: Design = serde_json::from_str(json).context("invalid Yosys JSON")?;
+    let modules = &design.modules.0;
+    let (name, module) = match top {
+        Some(top) => modules
+            .iter()
+            .find(|(name, _)| name == top)
+            .with_context(|| format!("no module `{top}`"))?,
+        None => {
+            let marked: Vec<_> = modules
+                .iter()
+                .filter(|(_, m)| m.attributes.contains_key("top"))
+                .collect();
+            match (marked.as_slice(), modules.as_slice()) {
+                ([top], _) => *top,
+                ([], [top]) => top,
+                _ => anyhow::bail!(
+                    "pick one of the modules {:?}",
+                    modules.iter().map(|(name, _)| name).collect::<Vec<_>>()
+                ),
+            }
+        }
+    };
+
+    let mut netlist = Netlist::default();
+    let mut nets: HashMap<u64, Net> = HashMap::new();
+    let mut signal = |netlist: &mut Netlist, bit: &Bit| -> anyhow::Result<Signal> {
+        match bit {
+            Bit::Net(n) => Ok(Signal::Net(*nets.entry(*n).or_insert_with(|| {
+                netlist.net_names.push(n.to_string());
+                netlist.net_names.len() - 1
+            }))),
+            Bit::Const(c) if c == "0" => Ok(Signal::Const(false)),
+            Bit::Const(c) if c == "1" => Ok(Signal::Const(true)),
+            Bit::Const(c) => anyhow::bail!("undefined bit `{c}` in module `{name}`"),
+        }
+    };
+
+    for (port, p) in &module.ports.0 {
+        let signals = p
+            .bits
+            .iter()
+            .map(|bit| signal(&mut netlist, bit))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        match p.direction.as_str() {
+            "input" => {
+                let nets = signals
+                    .into_iter()
+                    .map(|s| match s {
+                        Signal::Net(n) => Ok(n),
+                        Signal::Const(_) => anyhow::bail!("input `{port}` has a constant bit"),
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                netlist.inputs.push((port.clone(), nets));
+            }
+            "output" => netlist.outputs.push((port.clone(), signals)),
+            d => anyhow::bail!("port `{port}` has unsupported direction `{d}`"),
+        }
+    }
+
+    for (cell_name, c) in &module.cells.0 {
+        let (kind, ports) =
+            SimpleCell::parse(&c.cell_type).with_context(|| format!("cell `{cell_name}`"))?;
+        let port_bit = |port: &str| match c.connections.get(port).map(Vec::as_slice) {
+            Some([bit]) => Ok(bit),
+            _ => anyhow::bail!("cell `{cell_name}` needs a 1-bit `{port}` connection"),
+        };
+        let inputs = ports
+            .iter()
+            .map(|port| signal(&mut netlist, port_bit(port)?))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let output = match signal(&mut netlist, port_bit("Y")?)? {
+            Signal::Net(n) => n,
+            Signal::Const(_) => anyhow::bail!("cell `{cell_name}` drives a constant"),
+        };
+        netlist.cells.push(Cell {
+            kind: CellKind::Simple(kind),
+            inputs,
+            output,
+        });
+    }
+    netlist.to_circuit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `module top(input [1:0] a, input b, output [1:0] y); assign y = {a[1] | b, a[0] ^ b};`
+    const NETLIST: &str = r#"{
+      "creator": "Yosys",
+      "modules": {
+        "top": {
+          "attributes": { "top": "00000000000000000000000000000001" },
+          "ports": {
+            "a": { "direction": "input", "bits": [ 2, 3 ] },
+            "b": { "direction": "input", "bits": [ 4 ] },
+            "y": { "direction": "output", "bits": [ 5, 6 ] }
+          },
+          "cells": {
+            "$abc$1": {
+              "type": "$_XOR_",
+              "connections": { "A": [ 2 ], "B": [ 4 ], "Y": [ 5 ] }
+            },
+            "$abc$2": {
+              "type": "$_OR_",
+              "connections": { "A": [ 3 ], "B": [ 4 ], "Y": [ 6 ] }
+            }
+          }
+        }
+      }
+    }"#;
+
+    #[test]
+    fn imports_simple_cells() {
+        let ckt = parse_yosys_json(NETLIST, None).unwrap();
+        assert_eq!(ckt.input_groups, vec![2, 1]);
+        assert_eq!(ckt.output_groups, vec![2]);
+        for a in 0..4u8 {
+            for b in [false, true] {
+                let a_bits = vec![a & 1 == 1, a & 2 == 2];
+                let out = ckt.eval(&[a_bits.clone(), vec![b]]).unwrap();
+                assert_eq!(out, vec![vec![a_bits[0] ^ b, a_bits[1] | b]]);
+            }
+        }
+
+        let flop = NETLIST.replace("$_OR_", "$_DFF_P_");
+        let err = parse_yosys_json(&flop, None).unwrap_err();
+        assert!(format!("{err:#}").contains("unsupported cell type `$_DFF_P_`"));
+    }
+}
@@ -0,0 +1,108 @@
+//! Levelization: the gates grouped by depth.
+//!
+//! A gate is on level `d` if the longest path from the primary inputs to its output has `d + 1`
+//! gates, so every gate reads only primary inputs and outputs of lower levels, and the gates of
+//! one level can be evaluated or garbled in any order, or concurrently.
+
+use crate::{Circuit, WireId};
+
+/// Gate indices by level, in circuit order within a level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Levels {
+    gates: Vec<usize>,
+    /// level `d` is `gates[starts[d]..starts[d + 1]]`
+    starts: Vec<usize>,
+}
+
+impl Levels {
+    /// Levelize gates given as `(inputs, output)` wire ids, in evaluation order
+    pub fn new<G, I>(num_wires: usize, gates: G) -> Self
+    where
+        G: IntoIterator<Item = (I, WireId)>,
+        I: IntoIterator<Item = WireId>,
+    {
+        // number of levels below each wire, 0 for inputs
+        let mut depth = vec![0u32; num_wires];
+        let mut level_of = Vec::new();
+        let mut counts: Vec<usize> = Vec::new();
+        for (inputs, output) in gates {
+            let level = inputs.into_iter().map(|w| depth[w]).max().unwrap_or(0);
+            depth[output] = level + 1;
+            level_of.push(level);
+            let level = level as usize;
+            if counts.len() <= level {
+                counts.resize(level + 1, 0);
+            }
+            counts[level] += 1;
+        }
+
+        let mut starts = Vec::with_capacity(counts.len() + 1);
+        starts.push(0);
+        for count in counts {
+            starts.push(starts.last().unwrap() + count);
+        }
+        let mut next = starts.clone();
+        let mut gates = vec![0; level_of.len()];
+        for (g, level) in level_of.into_iter().enumerate() {
+            gates[next[level as usize]] = g;
+            next[level as usize] += 1;
+        }
+        Levels { gates, starts }
+    }
+
+    /// Number of levels, the depth of the circuit
+    pub fn len(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Indices of the gates on level `d`
+    pub fn level(&self, d: usize) -> &[usize] {
+        &self.gates[self.starts[d]..self.starts[d + 1]]
+    }
+
+    /// The levels from the inputs up
+    pub fn iter(&self) -> impl Iterator<Item = &[usize]> {
+        self.starts.windows(2).map(|w| &self.gates[w[0]..w[1]])
+    }
+}
+
+impl Circuit {
+    /// Group the gates by depth, assuming they are in evaluation order
+    pub fn levels(&self) -> Levels {
+        Levels::new(
+            self.num_wires,
+            self.gates
+                .iter()
+                .map(|g| (g.inputs.iter().copied(), g.output)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Circuit;
+
+    #[test]
+    fn levels_only_read_lower_levels() {
+        let ckt = Circuit::random(16, 2000, 0.5, 9);
+        let levels = ckt.levels();
+        assert_eq!(levels.len(), ckt.stats().unwrap().depth);
+        assert_eq!(levels.iter().map(<[usize]>::len).sum::<usize>(), 2000);
+
+        let mut level_of_wire = vec![None; ckt.num_wires];
+        for (d, level) in levels.iter().enumerate() {
+            assert!(level.windows(2).all(|w| w[0] < w[1]));
+            for &g in level {
+                let gate = &ckt.gates[g];
+                let below = gate.inputs.iter().filter_map(|&w| level_of_wire[w]).max();
+                assert!(below.is_none_or(|b| b < d));
+                assert_eq!(below.map_or(0, |b| b + 1), d);
+                level_of_wire[gate.output] = Some(d);
+            }
+        }
+    }
+}
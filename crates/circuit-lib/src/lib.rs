@@ -1,23 +1,101 @@
 use std::{
-    collections::HashSet,
+    borrow::Cow,
     fmt::{self, Display, Formatter},
-    fs,
-    io::{self, Write},
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
     path::Path,
 };
 
 use anyhow::Context;
-use rand::{prelude::*, rng};
+
+pub mod binary;
+pub mod builder;
+pub mod compose;
+pub mod equiv;
+pub mod export;
+pub mod format;
+pub mod hash;
+pub mod import;
+pub mod levels;
+pub mod opt;
+pub mod random;
+pub mod sim;
+pub mod stats;
 
 /// A wire is just an index into the global wire pool.
 pub type WireId = usize;
 
 /// gate types
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GateType {
     And,
     Xor,
     Inv,
+    /// constant 0 or 1 without inputs, Bristol's `EQ`
+    Const(bool),
+    /// copy of the input wire, Bristol's `EQW`
+    Buf,
+    Or,
+    Nand,
+    Nor,
+    Xnor,
+    /// arbitrary 2-input gate given by its truth table: bit `2a + b` is the output for inputs
+    /// `(a, b)`, so AND is `0x8` and OR is `0xE`. Written as `LUT<hex digit>`
+    Lut(u8),
+}
+
+impl GateType {
+    /// Number of input wires a gate of this type reads
+    pub fn arity(self) -> usize {
+        match self {
+            GateType::Inv | GateType::Buf => 1,
+            GateType::Const(_) => 0,
+            _ => 2,
+        }
+    }
+
+    /// Output of a gate of this type for the given input bits
+    pub fn eval(self, inputs: &[bool]) -> bool {
+        match self {
+            GateType::Const(value) => value,
+            GateType::Buf => inputs[0],
+            GateType::Inv => !inputs[0],
+            _ => {
+                let tt = self
+                    .truth_table()
+                    .expect("2-input gates have a truth table");
+                let row = 2 * usize::from(inputs[0]) + usize::from(inputs[1]);
+                (tt >> row) & 1 == 1
+            }
+        }
+    }
+
+    /// Truth table of a 2-input gate, in the bit order of [`GateType::Lut`]
+    pub fn truth_table(self) -> Option<u8> {
+        match self {
+            GateType::And => Some(0x8),
+            GateType::Xor => Some(0x6),
+            GateType::Or => Some(0xE),
+            GateType::Nand => Some(0x7),
+            GateType::Nor => Some(0x1),
+            GateType::Xnor => Some(0x9),
+            GateType::Lut(tt) => Some(tt & 0xF),
+            GateType::Inv | GateType::Buf | GateType::Const(_) => None,
+        }
+    }
+
+    /// Whether this is an AND up to the polarity of its inputs and output (an odd number of 1s
+    /// in the truth table), i.e. a gate that costs a four-row table under free-XOR
+    pub fn is_and_like(self) -> bool {
+        self.truth_table()
+            .is_some_and(|tt| tt.count_ones() % 2 == 1)
+    }
+
+    /// Whether the garbler builds a table for a gate of this type: AND-like gates, and INV
+    /// including the truth tables that negate one input and ignore the other
+    pub fn needs_table(self) -> bool {
+        self.is_and_like() || matches!(self, GateType::Inv | GateType::Lut(0x3 | 0x5))
+    }
 }
 
 impl Display for GateType {
@@ -26,13 +104,20 @@ impl Display for GateType {
             GateType::And => "AND",
             GateType::Xor => "XOR",
             GateType::Inv => "INV",
+            GateType::Const(_) => "EQ",
+            GateType::Buf => "EQW",
+            GateType::Or => "OR",
+            GateType::Nand => "NAND",
+            GateType::Nor => "NOR",
+            GateType::Xnor => "XNOR",
+            GateType::Lut(tt) => return write!(f, "LUT{:X}", tt & 0xF),
         };
         write!(f, "{s}")
     }
 }
 
 /// One gate in the circuit. The gates are assumed to have fan-out of 1
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Gate {
     pub gate_type: GateType,
     /// indices of input wires
@@ -45,6 +130,10 @@ impl Gate {
     /// Render this gate as: fan_in fan_out in0 ...in_n out
     pub fn to_bristol_string(&self) -> String {
         let out = self.output;
+        if let GateType::Const(value) = self.gate_type {
+            // EQ takes the constant in place of an input wire
+            return format!("1 1 {} {} EQ", u8::from(value), out);
+        }
         let fan_in = self.inputs.len();
         let inputs = self
             .inputs
@@ -56,6 +145,219 @@ impl Gate {
     }
 }
 
+/// Parse one gate line of the form: fan_in fan_out in0 ...in_n out0 ...out_m OP
+/// and append it to `gates`. `MAND` lines are lowered to one AND gate per output.
+fn parse_gates(line: &str, gates: &mut Vec<Gate>) -> anyhow::Result<()> {
+    let truncated = || format!("truncated gate line `{line}`");
+    // the op comes last but decides how to read the operands (EQ takes a constant)
+    let (operands, op) = line
+        .trim()
+        .rsplit_once(|c: char| c.is_ascii_whitespace())
+        .with_context(truncated)?;
+    let mut tok = operands.split_ascii_whitespace();
+    let wires = |tok: &mut std::str::SplitAsciiWhitespace, n: usize| {
+        (0..n)
+            .map(|_| Ok(tok.next().with_context(truncated)?.parse()?))
+            .collect::<anyhow::Result<Vec<WireId>>>()
+    };
+
+    let counts = wires(&mut tok, 2)?;
+    let (in_count, out_count) = (counts[0], counts[1]);
+
+    if op == "MAND" {
+        // 2n n a_1 ..a_n b_1 ..b_n o_1 ..o_n MAND computes o_i = a_i AND b_i
+        anyhow::ensure!(
+            in_count == 2 * out_count,
+            "MAND gate expects twice as many inputs as outputs in `{line}`"
+        );
+        let inputs = wires(&mut tok, in_count)?;
+        let outputs = wires(&mut tok, out_count)?;
+        anyhow::ensure!(tok.next().is_none(), "too many operands in `{line}`");
+        let (a, b) = inputs.split_at(out_count);
+        for ((&in0, &in1), &output) in a.iter().zip(b).zip(&outputs) {
+            gates.push(Gate {
+                gate_type: GateType::And,
+                inputs: vec![in0, in1],
+                output,
+            });
+        }
+        return Ok(());
+    }
+
+    anyhow::ensure!(out_count == 1, "{op} gate expects 1 output in `{line}`");
+    let gate_type = if op == "EQ" {
+        anyhow::ensure!(in_count == 1, "EQ gate expects 1 input in `{line}`");
+        match tok.next() {
+            Some("0") => GateType::Const(false),
+            Some("1") => GateType::Const(true),
+            _ => anyhow::bail!("EQ gate expects a constant 0 or 1 in `{line}`"),
+        }
+    } else {
+        parse_op(op)?
+    };
+    let arity = gate_type.arity();
+    let inputs = if let GateType::Const(_) = gate_type {
+        vec![]
+    } else {
+        anyhow::ensure!(
+            in_count == arity,
+            "{gate_type} gate expects {arity} inputs in `{line}`"
+        );
+        wires(&mut tok, in_count)?
+    };
+    let output = wires(&mut tok, 1)?[0];
+    anyhow::ensure!(tok.next().is_none(), "too many operands in `{line}`");
+
+    gates.push(Gate {
+        gate_type,
+        inputs,
+        output,
+    });
+    Ok(())
+}
+
+/// The gate type of a Bristol op other than `EQ` and `MAND`, which take their operands
+/// differently
+fn parse_op(op: &str) -> anyhow::Result<GateType> {
+    Ok(match op {
+        "AND" => GateType::And,
+        "XOR" => GateType::Xor,
+        "INV" | "NOT" => GateType::Inv,
+        "EQW" => GateType::Buf,
+        "OR" => GateType::Or,
+        "NAND" => GateType::Nand,
+        "NOR" => GateType::Nor,
+        "XNOR" => GateType::Xnor,
+        lut if lut.starts_with("LUT") => {
+            // exactly one hex digit, `from_str_radix` alone would also take a sign
+            let digit = &lut[3..];
+            anyhow::ensure!(
+                digit.len() == 1 && digit.bytes().all(|b| b.is_ascii_hexdigit()),
+                "LUT gate expects a hex truth table, got `{lut}`"
+            );
+            GateType::Lut(u8::from_str_radix(digit, 16)?)
+        }
+        other => anyhow::bail!("unsupported gate op `{}`", other),
+    })
+}
+
+/// Parse an I/O header line of the form: count size_1 ... size_count
+fn parse_io_header(line: &str) -> anyhow::Result<Vec<usize>> {
+    let mut tok = line.split_ascii_whitespace();
+    let count: usize = tok.next().context("empty I/O header line")?.parse()?;
+    let sizes = tok
+        .map(|t| Ok(t.parse()?))
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+    anyhow::ensure!(
+        sizes.len() == count,
+        "I/O header `{line}` declares {count} values but lists {}",
+        sizes.len()
+    );
+    Ok(sizes)
+}
+
+/// Render an I/O header line: count size_1 ... size_count
+fn io_header(groups: &[usize]) -> String {
+    std::iter::once(groups.len())
+        .chain(groups.iter().copied())
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Primary inputs and outputs derived from gate topology: inputs are the wires never driven by
+/// a gate, outputs are the wires driven by a gate but never used as a gate input. Both are
+/// returned in ascending wire order.
+fn io_from_topology(num_wires: usize, gates: &[Gate]) -> (Vec<WireId>, Vec<WireId>) {
+    let mut driven = WireSet::new(num_wires);
+    let mut used_in = WireSet::new(num_wires);
+    for g in gates {
+        driven.insert(g.output);
+        for &w in &g.inputs {
+            used_in.insert(w);
+        }
+    }
+
+    let inputs: Vec<WireId> = (0..num_wires).filter(|&w| !driven.contains(w)).collect();
+    let outputs: Vec<WireId> = (0..num_wires)
+        .filter(|&w| driven.contains(w) && !used_in.contains(w))
+        .collect();
+    (inputs, outputs)
+}
+
+/// Parse the `num_gates` gate lines following a header, the header counts a MAND line as a
+/// single gate
+fn read_gate_lines<R: BufRead>(
+    lines: &mut Lines<R>,
+    num_gates: usize,
+    num_wires: usize,
+) -> anyhow::Result<Vec<Gate>> {
+    let mut gates = Vec::with_capacity(num_gates);
+    for gate_lines in 0..num_gates {
+        let (line_no, line) = lines
+            .next_line()?
+            .with_context(|| format!("header declares {num_gates} gates but found {gate_lines}"))?;
+        let first = gates.len();
+        parse_gates(line, &mut gates).with_context(|| format!("line {line_no}"))?;
+        anyhow::ensure!(
+            gates[first..]
+                .iter()
+                .all(|g| g.output < num_wires && g.inputs.iter().all(|&w| w < num_wires)),
+            "line {line_no}: gate `{line}` refers to a wire outside 0..{num_wires}"
+        );
+    }
+    Ok(gates)
+}
+
+/// A set of wires, one bit per wire
+struct WireSet(Vec<u64>);
+
+impl WireSet {
+    fn new(num_wires: usize) -> Self {
+        WireSet(vec![0; num_wires.div_ceil(64)])
+    }
+
+    fn insert(&mut self, w: WireId) {
+        self.0[w / 64] |= 1 << (w % 64);
+    }
+
+    fn contains(&self, w: WireId) -> bool {
+        (self.0[w / 64] >> (w % 64)) & 1 == 1
+    }
+}
+
+/// The non-empty lines of a Bristol file, read one at a time into the same buffer
+struct Lines<R> {
+    reader: R,
+    buf: String,
+    line_no: usize,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn new(reader: R) -> Self {
+        Lines {
+            reader,
+            buf: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// The next non-empty line, trimmed, and its line number
+    fn next_line(&mut self) -> anyhow::Result<Option<(usize, &str)>> {
+        loop {
+            self.buf.clear();
+            if self.reader.read_line(&mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            self.line_no += 1;
+            if !self.buf.trim().is_empty() {
+                break;
+            }
+        }
+        Ok(Some((self.line_no, self.buf.trim())))
+    }
+}
+
 /// The full circuit.
 #[derive(Debug, Clone)]
 pub struct Circuit {
@@ -65,6 +367,10 @@ pub struct Circuit {
     pub inputs: Vec<WireId>,
     /// which wires are outputs
     pub outputs: Vec<WireId>,
+    /// sizes of the input values (e.g. one per party), summing to `inputs.len()`
+    pub input_groups: Vec<usize>,
+    /// sizes of the output values, summing to `outputs.len()`
+    pub output_groups: Vec<usize>,
     /// all gates in order
     pub gates: Vec<Gate>,
 }
@@ -89,241 +395,237 @@ impl Circuit {
     /// Parse a Bristol-format file at `path`, ignore the declared IO lines,
     /// and compute primary inputs/outputs from gate topology.
     pub fn from_bristol_file(path: &Path) -> anyhow::Result<Self> {
-        // 1) Read the whole file
-        let text = fs::read_to_string(path)
+        Ok(Self::from_bristol_fashion(path)?.with_io_from_topology())
+    }
+
+    /// Replace the declared inputs and outputs by those derived from the gates: the wires no
+    /// gate drives and the driven wires no gate reads, each as a single value.
+    pub fn with_io_from_topology(mut self) -> Self {
+        let (inputs, outputs) = io_from_topology(self.num_wires, &self.gates);
+        self.input_groups = vec![inputs.len()];
+        self.output_groups = vec![outputs.len()];
+        self.inputs = inputs;
+        self.outputs = outputs;
+        self
+    }
+
+    /// Parse a Bristol Fashion file at `path`, taking the primary inputs/outputs from the
+    /// header: inputs are the first wires and outputs the last ones, grouped into the declared
+    /// input and output values.
+    pub fn from_bristol_fashion(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
             .with_context(|| format!("failed to read Bristol file `{}`", path.display()))?;
+        Self::read_bristol_fashion(BufReader::with_capacity(1 << 20, file))
+            .with_context(|| format!("failed to parse Bristol file `{}`", path.display()))
+    }
 
-        let mut lines = text.lines().filter(|l| !l.trim().is_empty()).map(str::trim);
+    /// Parse a Bristol Fashion circuit line by line, so that only the gates are held in
+    /// memory and not the text.
+    pub fn read_bristol_fashion<R: BufRead>(reader: R) -> anyhow::Result<Self> {
+        let mut lines = Lines::new(reader);
 
         // Parse header_line1: <num_gates> <num_wires>
-        let header: Vec<&str> = lines
-            .next()
-            .context("missing header line")?
-            .split_ascii_whitespace()
-            .collect();
-        let num_gates: usize = header[0].parse()?;
-        let num_wires: usize = header[1].parse()?;
-
-        // Parse header_line2: Input description
-        // TODO: (mukesh) considers only 1 input, generalize it.
-        let header: Vec<&str> = lines
-            .next()
-            .context("missing header line")?
-            .split_ascii_whitespace()
-            .collect();
-        let _num_inputs: usize = header[0].parse()?;
-        let _num_input_wires: usize = header[1].parse()?;
-
-        // Parse header_line3: Output description
-        // TODO: (mukesh) considers only 1 output, generalize it.
-        let header: Vec<&str> = lines
-            .next()
-            .context("missing header line")?
-            .split_ascii_whitespace()
-            .collect();
-        let _num_outputs: usize = header[0].parse()?;
-        let _num_output_wires: usize = header[1].parse()?;
-
-        // Parse all the gates
-        let mut gates = Vec::with_capacity(num_gates);
-        for line in lines.take(num_gates) {
-            let mut tok = line.split_ascii_whitespace();
-            let in_count: usize = tok.next().unwrap().parse()?;
-            let _out_count: usize = tok.next().unwrap().parse()?;
-
-            let inputs = (0..in_count)
-                .map(|_| tok.next().unwrap().parse().unwrap())
-                .collect();
-
-            let output: usize = tok.next().unwrap().parse()?;
-
-            let op = tok.next().unwrap();
-            let gate_type = match op {
-                "AND" => GateType::And,
-                "XOR" => GateType::Xor,
-                "INV" => GateType::Inv,
-                other => anyhow::bail!("unsupported gate op `{}`", other),
-            };
-            gates.push(Gate {
-                gate_type,
-                inputs,
-                output,
-            });
-        }
+        let (_, header) = lines.next_line()?.context("missing header line")?;
+        let (num_gates, num_wires) = match *header.split_ascii_whitespace().collect::<Vec<_>>() {
+            [gates, wires] => (gates.parse::<usize>()?, wires.parse::<usize>()?),
+            _ => anyhow::bail!("malformed header line"),
+        };
 
-        // Build sets of *all* wires used as an output, and as an input
-        let mut driven: HashSet<WireId> = HashSet::new();
-        let mut used_in: HashSet<WireId> = HashSet::new();
-        for g in &gates {
-            driven.insert(g.output);
-            for &w in &g.inputs {
-                used_in.insert(w);
-            }
-        }
+        // Parse header_line2: <num_input_values> <wires of value 1> ...
+        let (_, line) = lines.next_line()?.context("missing input header line")?;
+        let input_groups = parse_io_header(line)?;
+
+        // Parse header_line3: <num_output_values> <wires of value 1> ...
+        let (_, line) = lines.next_line()?.context("missing output header line")?;
+        let output_groups = parse_io_header(line)?;
 
-        // Primary inputs = those never driven by any gate
-        let inputs: Vec<WireId> = (0..num_wires).filter(|w| !driven.contains(w)).collect();
+        let gates = read_gate_lines(&mut lines, num_gates, num_wires)?;
 
-        // Primary outputs = wires driven by some gate but never used as input
-        let outputs: Vec<WireId> = driven
-            .into_iter()
-            .filter(|w| !used_in.contains(w))
-            .collect();
+        let num_inputs: usize = input_groups.iter().sum();
+        let num_outputs: usize = output_groups.iter().sum();
+        anyhow::ensure!(
+            num_inputs <= num_wires && num_outputs <= num_wires,
+            "header declares more I/O wires than the circuit has"
+        );
 
         Ok(Circuit {
             num_wires,
-            inputs,
-            outputs,
+            inputs: (0..num_inputs).collect(),
+            outputs: (num_wires - num_outputs..num_wires).collect(),
+            input_groups,
+            output_groups,
             gates,
         })
     }
 
     pub fn from_bristol_file_no_header(path: &Path) -> anyhow::Result<Self> {
-        // Read the file
-        let text = fs::read_to_string(path)
+        let file = File::open(path)
             .with_context(|| format!("failed to read Bristol file `{}`", path.display()))?;
-
-        // Collect only non-empty trimmed lines
-        let lines = text.lines().filter(|l| !l.trim().is_empty()).map(str::trim);
+        let mut lines = Lines::new(BufReader::with_capacity(1 << 20, file));
 
         let mut gates = Vec::new();
         let mut max_wire = 0_usize;
 
-        for line in lines {
-            let mut tok = line.split_ascii_whitespace();
-            let in_count: usize = tok.next().unwrap().parse()?;
-            let _out_count: usize = tok.next().unwrap().parse()?;
-
-            let inputs: Vec<WireId> = (0..in_count)
-                .map(|_| {
-                    let w: WireId = tok.next().unwrap().parse().unwrap();
-                    max_wire = max_wire.max(w);
-                    w
-                })
-                .collect();
-
-            let output: WireId = tok.next().unwrap().parse()?;
-            max_wire = max_wire.max(output);
-
-            let op = tok.next().unwrap();
-            let gate_type = match op {
-                "AND" => GateType::And,
-                "XOR" => GateType::Xor,
-                "INV" => GateType::Inv,
-                other => anyhow::bail!("unsupported gate op `{}`", other),
-            };
-
-            gates.push(Gate {
-                gate_type,
-                inputs,
-                output,
-            });
+        while let Some((line_no, line)) = lines.next_line()? {
+            let first = gates.len();
+            parse_gates(line, &mut gates).with_context(|| format!("line {line_no}"))?;
+            max_wire = gates[first..]
+                .iter()
+                .flat_map(|g| g.inputs.iter().chain([&g.output]))
+                .fold(max_wire, |m, &w| m.max(w));
         }
 
         // Calculate total wires: highest wire index + 1
         let num_wires = max_wire + 1;
 
-        let mut driven: HashSet<WireId> = HashSet::new();
-        let mut used_in: HashSet<WireId> = HashSet::new();
-        for g in &gates {
-            driven.insert(g.output);
-            for &w in &g.inputs {
-                used_in.insert(w);
-            }
-        }
-
-        let inputs: Vec<WireId> = (0..num_wires).filter(|w| !driven.contains(w)).collect();
-        let outputs: Vec<WireId> = driven
-            .into_iter()
-            .filter(|w| !used_in.contains(w))
-            .collect();
-
         Ok(Circuit {
             num_wires,
-            inputs,
-            outputs,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            input_groups: Vec::new(),
+            output_groups: Vec::new(),
             gates,
-        })
+        }
+        .with_io_from_topology())
     }
 
-    /// Generate a random Boolean circuit with `gates` gates, `depth` layers,
-    /// and `num_inputs`, `num_outputs` wires.
-    pub fn random(num_inputs: usize, num_gates: usize, ratio_xor_to_and: f64) -> Self {
-        let mut rng = rng();
-        let mut gates = Vec::with_capacity(num_gates);
-
-        // The number of available wires is initially the number of input wires
-        let mut available: usize = num_inputs;
-
-        // total number of wires is equal to sum of input wires and number of gates, assuming each gate has fan-out of 1
-        let num_wires = num_inputs + num_gates;
+    /// Append a constant wire driven by an `EQ` gate and return it
+    pub fn add_const_wire(&mut self, value: bool) -> WireId {
+        let wire = self.num_wires;
+        self.num_wires += 1;
+        self.gates.push(Gate {
+            gate_type: GateType::Const(value),
+            inputs: vec![],
+            output: wire,
+        });
+        wire
+    }
 
-        for _ in 0..num_gates {
-            // TODO: (mukesh) For now I am considering only two gate types.
-            let x: f64 = rng.random();
-            let gate_type = if x < ratio_xor_to_and {
-                GateType::Xor
-            } else {
-                GateType::And
-            };
+    /// Whether inputs are the first wires and outputs the last ones, in order, as Bristol
+    /// Fashion (and the garbler) expect.
+    pub fn has_standard_layout(&self) -> bool {
+        let n_out = self.outputs.len();
+        self.inputs.iter().copied().eq(0..self.inputs.len())
+            && n_out <= self.num_wires
+            && self
+                .outputs
+                .iter()
+                .copied()
+                .eq(self.num_wires - n_out..self.num_wires)
+    }
 
-            let in0 = rng.random_range(0..available);
-            let in1 = rng.random_range(0..available);
+    /// Renumber the wires so that inputs come first and outputs last, keeping the gate order.
+    /// Inner wires are numbered in the order their gates appear; wires that are neither
+    /// inputs nor driven by a gate are dropped.
+    pub fn with_standard_layout(&self) -> anyhow::Result<Self> {
+        let mut map: Vec<Option<WireId>> = vec![None; self.num_wires];
 
-            //increment the available wires to add the output of the gate being generated
-            let out = available;
-            available += 1;
+        for (new, &w) in self.inputs.iter().enumerate() {
+            anyhow::ensure!(map[w].is_none(), "wire {w} is listed twice as an input");
+            map[w] = Some(new);
+        }
 
-            gates.push(Gate {
-                gate_type,
-                inputs: vec![in0, in1],
-                output: out,
-            });
+        let mut is_output = vec![false; self.num_wires];
+        for &w in &self.outputs {
+            anyhow::ensure!(
+                map[w].is_none() && !is_output[w],
+                "output wire {w} is also an input or listed twice"
+            );
+            is_output[w] = true;
         }
 
-        let mut driven: HashSet<WireId> = HashSet::new();
-        let mut used_in: HashSet<WireId> = HashSet::new();
-        for g in &gates {
-            driven.insert(g.output);
-            for &w in &g.inputs {
-                used_in.insert(w);
+        let mut driven = vec![false; self.num_wires];
+        let mut next = self.inputs.len();
+        for g in &self.gates {
+            anyhow::ensure!(
+                !driven[g.output] && map[g.output].is_none(),
+                "wire {} is an input or driven more than once",
+                g.output
+            );
+            driven[g.output] = true;
+            if !is_output[g.output] {
+                map[g.output] = Some(next);
+                next += 1;
             }
         }
 
-        let inputs: Vec<WireId> = (0..num_wires).filter(|w| !driven.contains(w)).collect();
-        let outputs: Vec<WireId> = driven
-            .into_iter()
-            .filter(|w| !used_in.contains(w))
-            .collect();
+        let num_wires = next + self.outputs.len();
+        for (i, &w) in self.outputs.iter().enumerate() {
+            anyhow::ensure!(driven[w], "output wire {w} is never driven");
+            map[w] = Some(next + i);
+        }
+
+        let relabel = |w: WireId| map[w].with_context(|| format!("wire {w} is never driven"));
+        let gates = self
+            .gates
+            .iter()
+            .map(|g| {
+                Ok(Gate {
+                    gate_type: g.gate_type,
+                    inputs: g
+                        .inputs
+                        .iter()
+                        .map(|&w| relabel(w))
+                        .collect::<anyhow::Result<_>>()?,
+                    output: relabel(g.output)?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
 
-        Circuit {
+        Ok(Circuit {
             num_wires,
-            inputs,
-            outputs,
+            inputs: (0..self.inputs.len()).collect(),
+            outputs: (next..num_wires).collect(),
+            input_groups: self.input_groups.clone(),
+            output_groups: self.output_groups.clone(),
             gates,
-        }
+        })
     }
 
-    /// Write the circuit in standard bristol fashion, header and one gate per line
+    /// Write the circuit in standard bristol fashion, header and one gate per line.
+    ///
+    /// Every input and output group is declared as its own value, and the wires are renumbered
+    /// (see [`Circuit::with_standard_layout`]) if inputs aren't first and outputs last already.
     pub fn write_bristol_fashion<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let ckt = self.in_standard_layout()?;
+
         // Line 1: <Gate Count> <Wire Count>
-        writeln!(w, "{} {}", self.get_gate_count(), self.get_wire_count())?;
+        writeln!(w, "{} {}", ckt.get_gate_count(), ckt.get_wire_count())?;
+
+        // Line 2: <number of input values> <wires of each value>
+        writeln!(w, "{}", io_header(&ckt.input_groups))?;
 
-        // Line 2: List the number of inputs and number of input wires for each input
-        // for now, we will simply put all input wires as a single input val
-        writeln!(w, "1 {}", self.get_input_wire_count())?;
+        // Line 3: <number of output values> <wires of each value>
+        writeln!(w, "{}", io_header(&ckt.output_groups))?;
 
-        // Line 3: List the number of outputs and number of output wires for each output
-        // for now, we will simply put all output wires as a single output val
-        writeln!(w, "1 {}", self.get_output_wire_count())?;
+        // a blank line separates the header from the gates, as in the reference circuits
+        writeln!(w)?;
 
         // write the gates one per line
-        for gate in &self.gates {
+        for gate in &ckt.gates {
             writeln!(w, "{}", gate.to_bristol_string())?;
         }
         Ok(())
     }
 
+    /// The circuit with inputs first and outputs last, for the writers of formats that only give
+    /// the I/O sizes
+    fn in_standard_layout(&self) -> io::Result<Cow<'_, Circuit>> {
+        let invalid = |e: anyhow::Error| io::Error::new(io::ErrorKind::InvalidInput, e);
+        if self.input_groups.iter().sum::<usize>() != self.get_input_wire_count()
+            || self.output_groups.iter().sum::<usize>() != self.get_output_wire_count()
+        {
+            return Err(invalid(anyhow::anyhow!(
+                "I/O groups don't add up to the number of I/O wires"
+            )));
+        }
+        Ok(if self.has_standard_layout() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.with_standard_layout().map_err(invalid)?)
+        })
+    }
+
     /// Write the circuit in standard bristol fashion without header
     pub fn write_bristol_fashion_no_header<W: Write>(&self, mut w: W) -> io::Result<()> {
         // write the gates one per line
@@ -333,3 +635,202 @@ impl Circuit {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::example_path;
+    use std::fs;
+
+    #[test]
+    fn header_and_topology_agree_on_example1() {
+        let from_header = Circuit::from_bristol_fashion(&example_path("example1")).unwrap();
+        let from_topology = Circuit::from_bristol_file(&example_path("example1")).unwrap();
+
+        assert_eq!(from_header.inputs, vec![0, 1, 2, 3, 4]);
+        assert_eq!(from_header.outputs, vec![8]);
+        assert_eq!(from_header.input_groups, vec![5]);
+        assert_eq!(from_header.output_groups, vec![1]);
+        assert_eq!(from_header.inputs, from_topology.inputs);
+        assert_eq!(from_header.outputs, from_topology.outputs);
+        assert_eq!(from_header.gates, from_topology.gates);
+        assert!(from_header.has_standard_layout());
+    }
+
+    #[test]
+    fn streaming_parser_reports_line_numbers() {
+        let text = fs::read_to_string(example_path("example1")).unwrap();
+        let from_reader = Circuit::read_bristol_fashion(text.as_bytes()).unwrap();
+        let from_file = Circuit::from_bristol_fashion(&example_path("example1")).unwrap();
+        assert_eq!(from_reader.gates, from_file.gates);
+        assert_eq!(from_reader.input_groups, from_file.input_groups);
+
+        let err = |text: &str| {
+            format!(
+                "{:#}",
+                Circuit::read_bristol_fashion(text.as_bytes()).unwrap_err()
+            )
+        };
+        assert!(err("2 4\n1 2\n1 1\n\n2 1 0 1 2 AND\r\n2 1 0 4 3 XOR\n").contains("line 6"));
+        assert!(err("2 4\n1 2\n1 1\n2 1 0 1 2 AND\n").contains("declares 2 gates but found 1"));
+        assert!(err("1 3\n1 2\n1 1\n2 1 0 1 2 AND extra\n").contains("line 4"));
+    }
+
+    #[test]
+    fn standard_layout_moves_outputs_last() {
+        // inputs 0,1; outputs 2 = 0 AND 1 (also used by the XOR) and 4 = INV(2 XOR 0)
+        let ckt = Circuit {
+            num_wires: 5,
+            inputs: vec![0, 1],
+            outputs: vec![2, 4],
+            input_groups: vec![1, 1],
+            output_groups: vec![2],
+            gates: vec![
+                Gate {
+                    gate_type: GateType::And,
+                    inputs: vec![0, 1],
+                    output: 2,
+                },
+                Gate {
+                    gate_type: GateType::Xor,
+                    inputs: vec![2, 0],
+                    output: 3,
+                },
+                Gate {
+                    gate_type: GateType::Inv,
+                    inputs: vec![3],
+                    output: 4,
+                },
+            ],
+        };
+        assert!(!ckt.has_standard_layout());
+
+        let relabeled = ckt.with_standard_layout().unwrap();
+        assert!(relabeled.has_standard_layout());
+        assert_eq!(relabeled.num_wires, 5);
+        assert_eq!(relabeled.outputs, vec![3, 4]);
+        assert_eq!(relabeled.gates[0].output, 3);
+        assert_eq!(relabeled.gates[1].inputs, vec![3, 0]);
+        assert_eq!(relabeled.gates[1].output, 2);
+        assert_eq!(relabeled.gates[2].inputs, vec![2]);
+    }
+
+    #[test]
+    fn writer_keeps_io_groups_and_relabels() {
+        let config = random::RandomCircuitConfig {
+            garbler_inputs: Some(3),
+            xor_ratio: 0.5,
+            seed: 5,
+            ..random::RandomCircuitConfig::new(8, 100)
+        };
+        let mut ckt = Circuit::random_with(&config).unwrap();
+        let n_out = ckt.outputs.len();
+        ckt.output_groups = vec![1, n_out - 1];
+        assert!(!ckt.has_standard_layout());
+
+        let path = std::env::temp_dir().join("circuit-lib-writer-groups.bristol");
+        ckt.write_bristol_fashion(fs::File::create(&path).unwrap())
+            .unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("100 108"));
+        assert_eq!(lines.next(), Some("2 3 5"));
+        assert_eq!(lines.next(), Some(format!("2 1 {}", n_out - 1).as_str()));
+        assert_eq!(lines.next(), Some(""));
+
+        let back = Circuit::from_bristol_fashion(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let expected = ckt.with_standard_layout().unwrap();
+        assert_eq!(back.input_groups, vec![3, 5]);
+        assert_eq!(back.output_groups, vec![1, n_out - 1]);
+        assert_eq!(back.gates, expected.gates);
+        assert_eq!(back.outputs, expected.outputs);
+    }
+
+    #[test]
+    fn random_is_reproducible_from_seed() {
+        let a = Circuit::random(16, 500, 0.8, 42);
+        let b = Circuit::random(16, 500, 0.8, 42);
+        let c = Circuit::random(16, 500, 0.8, 43);
+
+        assert_eq!(a.gates, b.gates);
+        assert_eq!(a.outputs, b.outputs);
+        assert_ne!(a.gates, c.gates);
+        assert!(a.outputs.windows(2).all(|w| w[0] < w[1]));
+
+        let mut text_a = Vec::new();
+        let mut text_b = Vec::new();
+        a.write_bristol_fashion(&mut text_a).unwrap();
+        b.write_bristol_fashion(&mut text_b).unwrap();
+        assert_eq!(text_a, text_b);
+    }
+
+    #[test]
+    fn parses_bristol_fashion_aliases() {
+        let mut gates = Vec::new();
+        for line in [
+            "1 1 0 5 EQ",
+            "1 1 1 6 EQ",
+            "1 1 2 7 EQW",
+            "1 1 3 8 NOT",
+            "4 2 0 1 2 3 9 10 MAND",
+        ] {
+            parse_gates(line, &mut gates).unwrap();
+        }
+        let gate = |gate_type, inputs: &[WireId], output| Gate {
+            gate_type,
+            inputs: inputs.to_vec(),
+            output,
+        };
+        assert_eq!(
+            gates,
+            vec![
+                gate(GateType::Const(false), &[], 5),
+                gate(GateType::Const(true), &[], 6),
+                gate(GateType::Buf, &[2], 7),
+                gate(GateType::Inv, &[3], 8),
+                gate(GateType::And, &[0, 2], 9),
+                gate(GateType::And, &[1, 3], 10),
+            ]
+        );
+        assert_eq!(gates[1].to_bristol_string(), "1 1 1 6 EQ");
+        assert_eq!(gates[2].to_bristol_string(), "1 1 2 7 EQW");
+
+        gates.clear();
+        for line in [
+            "2 1 0 1 2 OR",
+            "2 1 0 1 3 NAND",
+            "2 1 0 1 4 NOR",
+            "2 1 0 1 5 XNOR",
+            "2 1 0 1 6 LUTb",
+        ] {
+            parse_gates(line, &mut gates).unwrap();
+        }
+        let types: Vec<GateType> = gates.iter().map(|g| g.gate_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                GateType::Or,
+                GateType::Nand,
+                GateType::Nor,
+                GateType::Xnor,
+                GateType::Lut(0xB)
+            ]
+        );
+        assert_eq!(gates[4].to_bristol_string(), "2 1 0 1 6 LUTB");
+    }
+
+    #[test]
+    fn rejects_unknown_gates_and_bad_headers() {
+        let mut gates = Vec::new();
+        assert!(parse_gates("2 1 0 1 2 NAN", &mut gates).is_err());
+        assert!(parse_gates("2 1 0 1 2 LUT10", &mut gates).is_err());
+        assert!(parse_gates("2 1 0 1 2 LUT+8", &mut gates).is_err());
+        assert!(parse_gates("2 1 0 1 2 LUT", &mut gates).is_err());
+        assert!(parse_gates("2 1 0 2 AND", &mut gates).is_err());
+        assert!(parse_gates("1 1 2 3 EQ", &mut gates).is_err());
+        assert!(parse_gates("3 2 0 1 2 3 4 MAND", &mut gates).is_err());
+        assert!(parse_io_header("2 64").is_err());
+        assert_eq!(parse_io_header("2 64 64").unwrap(), vec![64, 64]);
+    }
+}
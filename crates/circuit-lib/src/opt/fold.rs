@@ -0,0 +1,176 @@
+//! Constant propagation.
+
+use crate::{Circuit, Gate, GateType, WireId};
+
+/// What is known about a wire while folding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// carries the same bit as this (kept) wire
+    Wire(WireId),
+    Const(bool),
+}
+
+/// What a gate folds into, inputs referred to by their position in the gate.
+enum Folded {
+    Const(bool),
+    /// the output equals one of the inputs
+    Copy(usize),
+    /// keep a gate of this type, reading these inputs
+    Gate(GateType, Vec<usize>),
+}
+
+/// A function of a single input given by its outputs `(f(0), f(1))`.
+fn unary(f0: bool, f1: bool, input: usize) -> Folded {
+    match (f0, f1) {
+        (false, true) => Folded::Copy(input),
+        (true, false) => Folded::Gate(GateType::Inv, vec![input]),
+        (v, _) => Folded::Const(v),
+    }
+}
+
+fn fold_gate(gate_type: GateType, inputs: &[Value]) -> Folded {
+    if gate_type.truth_table().is_none() {
+        return match (gate_type, inputs) {
+            (GateType::Const(v), _) => Folded::Const(v),
+            (_, &[Value::Const(v)]) => Folded::Const(gate_type.eval(&[v])),
+            (GateType::Buf, _) => Folded::Copy(0),
+            _ => Folded::Gate(gate_type, vec![0]),
+        };
+    }
+    let bit = |a: bool, b: bool| gate_type.eval(&[a, b]);
+
+    match *inputs {
+        [Value::Const(a), Value::Const(b)] => Folded::Const(bit(a, b)),
+        [Value::Const(a), _] => unary(bit(a, false), bit(a, true), 1),
+        [_, Value::Const(b)] => unary(bit(false, b), bit(true, b), 0),
+        [a, b] if a == b => unary(bit(false, false), bit(true, true), 0),
+        // the truth table may ignore one of the inputs
+        _ if bit(false, false) == bit(false, true) && bit(true, false) == bit(true, true) => {
+            unary(bit(false, false), bit(true, false), 0)
+        }
+        _ if bit(false, false) == bit(true, false) && bit(false, true) == bit(true, true) => {
+            unary(bit(false, false), bit(false, true), 1)
+        }
+        _ => Folded::Gate(gate_type, vec![0, 1]),
+    }
+}
+
+impl Circuit {
+    /// Fold gates with constant inputs, and gates whose output doesn't depend on one of their
+    /// inputs (`x AND 0`, `x XOR x`, `EQW`, ...), assuming the gates are in evaluation order.
+    ///
+    /// Folded gates are removed and their readers rewired to the constant or the surviving
+    /// wire; a gate with one constant input may turn into an INV. Wire ids are kept, and primary
+    /// outputs that fold away are driven by an `EQ` or `EQW` gate at the end of the circuit.
+    pub fn fold_constants(&self) -> Circuit {
+        let mut value: Vec<Value> = (0..self.num_wires).map(Value::Wire).collect();
+        let mut gates = Vec::with_capacity(self.gates.len());
+
+        for g in &self.gates {
+            let inputs: Vec<Value> = g.inputs.iter().map(|&w| value[w]).collect();
+            value[g.output] = match fold_gate(g.gate_type, &inputs) {
+                Folded::Const(v) => Value::Const(v),
+                Folded::Copy(i) => inputs[i],
+                Folded::Gate(gate_type, kept) => {
+                    let inputs = kept
+                        .iter()
+                        .map(|&i| match inputs[i] {
+                            Value::Wire(w) => w,
+                            Value::Const(_) => unreachable!("constant inputs are folded"),
+                        })
+                        .collect();
+                    gates.push(Gate {
+                        gate_type,
+                        inputs,
+                        output: g.output,
+                    });
+                    Value::Wire(g.output)
+                }
+            };
+        }
+
+        for &w in &self.outputs {
+            match value[w] {
+                Value::Const(v) => gates.push(Gate {
+                    gate_type: GateType::Const(v),
+                    inputs: vec![],
+                    output: w,
+                }),
+                Value::Wire(x) if x != w => gates.push(Gate {
+                    gate_type: GateType::Buf,
+                    inputs: vec![x],
+                    output: w,
+                }),
+                Value::Wire(_) => {}
+            }
+        }
+
+        Circuit {
+            num_wires: self.num_wires,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            input_groups: self.input_groups.clone(),
+            output_groups: self.output_groups.clone(),
+            gates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{assert_equivalent, circuit, gate};
+
+    #[test]
+    fn folds_constants_through_gates() {
+        let ckt = circuit(
+            2,
+            vec![
+                gate(GateType::Const(false), &[], 2),
+                gate(GateType::Const(true), &[], 3),
+                gate(GateType::And, &[0, 2], 4),        // 0
+                gate(GateType::Xor, &[1, 3], 5),        // !b
+                gate(GateType::Or, &[4, 5], 6),         // !b
+                gate(GateType::Xor, &[0, 0], 7),        // 0
+                gate(GateType::Inv, &[7], 8),           // 1
+                gate(GateType::And, &[6, 8], 9),        // !b
+                gate(GateType::Nand, &[0, 1], 10),      // kept
+                gate(GateType::Lut(0xC), &[10, 1], 11), // projection on the first input
+            ],
+            vec![9, 8, 11, 4],
+        );
+        let folded = ckt.fold_constants();
+        assert_equivalent(&ckt, &folded);
+
+        let types: Vec<GateType> = folded.gates.iter().map(|g| g.gate_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                GateType::Inv,
+                GateType::Nand,
+                GateType::Buf,
+                GateType::Const(true),
+                GateType::Buf,
+                GateType::Const(false),
+            ]
+        );
+        assert_eq!(folded.gates[0], gate(GateType::Inv, &[1], 5));
+        assert_eq!(folded.gates[2], gate(GateType::Buf, &[5], 9));
+    }
+
+    #[test]
+    fn leaves_constant_free_circuits_alone() {
+        let ckt = Circuit::random(6, 300, 0.7, 11);
+        let folded = ckt.fold_constants();
+        // random gates may read the same wire twice, those do fold
+        let same_inputs = ckt
+            .gates
+            .iter()
+            .filter(|g| g.inputs.len() == 2 && g.inputs[0] == g.inputs[1])
+            .count();
+        if same_inputs == 0 {
+            assert_eq!(folded.gates, ckt.gates);
+        }
+        assert_equivalent(&ckt, &folded);
+    }
+}
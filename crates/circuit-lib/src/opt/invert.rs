@@ -0,0 +1,199 @@
+//! Inverter absorption: removes every INV gate.
+//!
+//! Each wire is tracked as a kept wire plus a polarity. An INV only flips the polarity, XOR
+//! passes the polarities of its inputs on to its output, and any other 2-input gate absorbs
+//! them into its truth table (an AND of an inverted input becomes a `LUT` gate, which garbles
+//! with the same four rows). Outputs that end up inverted are XORed with a constant-one wire.
+
+use crate::{Circuit, Gate, GateType, WireId};
+
+/// Truth table of `f(a ^ inv_a, b ^ inv_b)`, for `f` given by `tt`
+pub(super) fn flip_inputs(tt: u8, inv_a: bool, inv_b: bool) -> u8 {
+    (0..4).fold(0, |acc, row| {
+        let src = row ^ (usize::from(inv_a) << 1) ^ usize::from(inv_b);
+        acc | (((tt >> src) & 1) << row)
+    })
+}
+
+impl Circuit {
+    /// Rewrite the circuit without INV gates, assuming the gates are in evaluation order.
+    ///
+    /// `XNOR` gates become `XOR` and any gate reading an inverted wire has the inversion folded
+    /// into its truth table, so gates other than AND and XOR may turn into `LUT` gates. Outputs
+    /// that would be inverted are computed as `XOR` with a constant-one wire; that wire and the
+    /// ones the inverted outputs are computed on are appended to the circuit, so inputs and
+    /// outputs keep their wire ids but the layout may no longer be standard.
+    pub fn absorb_inverters(&self) -> Circuit {
+        // value of wire w is `base[w] ^ inverted[w]`
+        let mut base: Vec<WireId> = (0..self.num_wires).collect();
+        let mut inverted = vec![false; self.num_wires];
+        let mut gates = Vec::with_capacity(self.gates.len());
+        let mut num_wires = self.num_wires;
+        let mut is_output = vec![false; self.num_wires];
+        for &w in &self.outputs {
+            is_output[w] = true;
+        }
+
+        for g in &self.gates {
+            let out = g.output;
+            let (gate_type, inputs, inv) = match (g.gate_type, g.inputs.as_slice()) {
+                (GateType::Inv, &[x]) | (GateType::Buf, &[x]) => {
+                    base[out] = base[x];
+                    inverted[out] = inverted[x] ^ (g.gate_type == GateType::Inv);
+                    continue;
+                }
+                (GateType::Const(v), _) => (GateType::Const(v), vec![], false),
+                (gate_type, &[a, b]) => {
+                    let tt = gate_type
+                        .truth_table()
+                        .expect("2-input gates have a truth table");
+                    let tt = flip_inputs(tt, inverted[a], inverted[b]);
+                    let (ba, bb) = (base[a], base[b]);
+                    match tt {
+                        0x0 | 0xF => (GateType::Const(tt != 0), vec![], false),
+                        // projections on one input
+                        0xC | 0x3 | 0xA | 0x5 => {
+                            base[out] = if tt == 0xC || tt == 0x3 { ba } else { bb };
+                            inverted[out] = tt & 1 == 1;
+                            continue;
+                        }
+                        0x6 | 0x9 => (GateType::Xor, vec![ba, bb], tt == 0x9),
+                        // one row differs from the others: an AND up to input polarity, with
+                        // the output polarity kept on the wire
+                        _ => {
+                            let inv = tt.count_ones() == 3;
+                            let tt = if inv { !tt & 0xF } else { tt };
+                            let gate_type = if tt == 0x8 {
+                                GateType::And
+                            } else {
+                                GateType::Lut(tt)
+                            };
+                            (gate_type, vec![ba, bb], inv)
+                        }
+                    }
+                }
+                (gate_type, inputs) => {
+                    unreachable!("{gate_type} gate with {} inputs", inputs.len())
+                }
+            };
+            // an inverted output is XORed into place at the end, so its gate needs another wire
+            let target = if inv && is_output[out] {
+                num_wires += 1;
+                num_wires - 1
+            } else {
+                out
+            };
+            gates.push(Gate {
+                gate_type,
+                inputs,
+                output: target,
+            });
+            base[out] = target;
+            inverted[out] = inv;
+        }
+
+        let mut ckt = Circuit {
+            num_wires,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            input_groups: self.input_groups.clone(),
+            output_groups: self.output_groups.clone(),
+            gates,
+        };
+
+        let mut one = None;
+        for &w in &self.outputs {
+            let (b, inv) = (base[w], inverted[w]);
+            if inv {
+                let one = *one.get_or_insert_with(|| ckt.add_const_wire(true));
+                ckt.gates.push(Gate {
+                    gate_type: GateType::Xor,
+                    inputs: vec![b, one],
+                    output: w,
+                });
+            } else if b != w {
+                ckt.gates.push(Gate {
+                    gate_type: GateType::Buf,
+                    inputs: vec![b],
+                    output: w,
+                });
+            }
+        }
+        ckt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{assert_equivalent, circuit, gate};
+
+    #[test]
+    fn flip_inputs_matches_eval() {
+        for tt in 0..16u8 {
+            for (inv_a, inv_b) in [(false, false), (false, true), (true, false), (true, true)] {
+                let flipped = GateType::Lut(flip_inputs(tt, inv_a, inv_b));
+                for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+                    assert_eq!(
+                        flipped.eval(&[a, b]),
+                        GateType::Lut(tt).eval(&[a ^ inv_a, b ^ inv_b])
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inverters_are_absorbed() {
+        let ckt = circuit(
+            3,
+            vec![
+                gate(GateType::Inv, &[0], 3),
+                gate(GateType::Xor, &[3, 1], 4), // NOT (a XOR b)
+                gate(GateType::And, &[4, 2], 5), // NOT x AND c
+                gate(GateType::Inv, &[5], 6),
+                gate(GateType::Nand, &[6, 3], 7), // NOT (NOT y AND NOT a) = y OR a
+                gate(GateType::Inv, &[4], 8),     // a XOR b, inverted twice
+                gate(GateType::Xnor, &[1, 2], 9),
+                gate(GateType::Inv, &[2], 10),
+            ],
+            vec![7, 8, 9, 10, 6],
+        );
+        let absorbed = ckt.absorb_inverters();
+        assert_equivalent(&ckt, &absorbed);
+        assert!(absorbed.gates.iter().all(|g| g.gate_type != GateType::Inv));
+
+        let types: Vec<GateType> = absorbed.gates.iter().map(|g| g.gate_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                GateType::Xor,
+                GateType::Lut(0x2),
+                GateType::Lut(0x1),
+                GateType::Xor,
+                GateType::Const(true),
+                GateType::Xor,
+                GateType::Buf,
+                GateType::Xor,
+                GateType::Xor,
+                GateType::Xor,
+            ]
+        );
+    }
+
+    #[test]
+    fn random_circuits_with_inverters_stay_equivalent() {
+        for seed in 0..4 {
+            let ckt = Circuit::random_with(&crate::random::RandomCircuitConfig {
+                xor_ratio: 0.5,
+                inv_ratio: 0.2,
+                seed,
+                ..crate::random::RandomCircuitConfig::new(8, 300)
+            })
+            .unwrap();
+            let absorbed = ckt.absorb_inverters();
+            assert!(absorbed.gates.iter().all(|g| g.gate_type != GateType::Inv));
+            assert_equivalent(&ckt, &absorbed);
+        }
+    }
+}
@@ -0,0 +1,491 @@
+//! Multiplicative-complexity reduction by 3-input cut rewriting.
+//!
+//! Under free-XOR only AND-like gates cost a table. For every gate the pass enumerates cuts of
+//! at most three wires below it, looks the function of the cone up in a database of AND-minimal
+//! XOR-AND implementations of all 256 functions of three variables, and replaces the cone when
+//! that implementation needs fewer ANDs than the gates that die with the cone.
+
+use std::sync::OnceLock;
+
+use super::invert::flip_inputs;
+use crate::{Circuit, Gate, GateType, WireId};
+
+/// Truth tables of the three variables: bit `i` is the value for `x_j = (i >> j) & 1`
+const VARS: [u8; 3] = [0xAA, 0xCC, 0xF0];
+
+/// Cuts kept per wire, smallest first
+const MAX_CUTS: usize = 12;
+
+/// XOR of the variables selected by `mask`, negated if `negated`. Variables are the three
+/// inputs followed by the outputs of the ANDs computed so far.
+#[derive(Debug, Clone, Copy)]
+struct Linear {
+    mask: u8,
+    negated: bool,
+}
+
+impl Linear {
+    fn eval(self, vars: &[u8]) -> u8 {
+        let v = vars
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| (self.mask >> j) & 1 == 1)
+            .fold(0, |acc, (_, &v)| acc ^ v);
+        if self.negated { !v } else { v }
+    }
+
+    /// Every linear function of the first `num_vars` variables whose mask has a bit of `must`
+    fn all(num_vars: usize, must: u8) -> impl Iterator<Item = Linear> {
+        (0..1u8 << num_vars)
+            .filter(move |mask| mask & must != 0 || must == 0)
+            .flat_map(|mask| [false, true].map(|negated| Linear { mask, negated }))
+    }
+}
+
+/// AND-minimal XOR-AND implementation of a function of three variables
+#[derive(Debug, Clone)]
+struct Recipe {
+    ands: Vec<(Linear, Linear)>,
+    out: Linear,
+}
+
+/// The implementations of all 256 functions, indexed by truth table. Every function of three
+/// variables needs at most two ANDs, so a search over one and two ANDs finds them all.
+fn recipes() -> &'static [Recipe] {
+    static RECIPES: OnceLock<Vec<Recipe>> = OnceLock::new();
+    RECIPES.get_or_init(|| {
+        let mut db: Vec<Option<Recipe>> = vec![None; 256];
+        for out in Linear::all(3, 0) {
+            db[usize::from(out.eval(&VARS))].get_or_insert(Recipe { ands: vec![], out });
+        }
+
+        // AND operands never need to be constant, that would be a cheaper function
+        let operands = |num_vars| {
+            let all: Vec<Linear> = Linear::all(num_vars, 0xFF).collect();
+            let mut pairs = Vec::new();
+            for (i, &a) in all.iter().enumerate() {
+                pairs.extend(all[i..].iter().map(|&b| (a, b)));
+            }
+            pairs
+        };
+
+        let first = operands(3);
+        for &(a, b) in &first {
+            let vars = [VARS[0], VARS[1], VARS[2], a.eval(&VARS) & b.eval(&VARS)];
+            for out in Linear::all(4, 1 << 3) {
+                db[usize::from(out.eval(&vars))].get_or_insert_with(|| Recipe {
+                    ands: vec![(a, b)],
+                    out,
+                });
+            }
+        }
+
+        let second = operands(4);
+        'search: for &(a, b) in &first {
+            let mut vars = vec![VARS[0], VARS[1], VARS[2], a.eval(&VARS) & b.eval(&VARS)];
+            for &(c, d) in &second {
+                vars.truncate(4);
+                vars.push(c.eval(&vars) & d.eval(&vars));
+                for out in Linear::all(5, 1 << 4) {
+                    db[usize::from(out.eval(&vars))].get_or_insert_with(|| Recipe {
+                        ands: vec![(a, b), (c, d)],
+                        out,
+                    });
+                }
+                if db.iter().all(Option::is_some) {
+                    break 'search;
+                }
+            }
+        }
+
+        db.into_iter()
+            .map(|r| r.expect("every function of three variables has at most two ANDs"))
+            .collect()
+    })
+}
+
+/// A set of at most three wires every path to a gate goes through, and the gate's value as a
+/// function of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cut {
+    leaves: [WireId; 3],
+    len: usize,
+    tt: u8,
+}
+
+impl Cut {
+    fn trivial(w: WireId) -> Cut {
+        Cut {
+            leaves: [w, 0, 0],
+            len: 1,
+            tt: VARS[0],
+        }
+    }
+
+    fn leaves(&self) -> &[WireId] {
+        &self.leaves[..self.len]
+    }
+
+    /// The cut's function over `to`, a superset of its leaves
+    fn expand(&self, to: &[WireId]) -> u8 {
+        let pos: Vec<usize> = self
+            .leaves()
+            .iter()
+            .map(|l| to.iter().position(|t| t == l).expect("leaves are a subset"))
+            .collect();
+        (0..8).fold(0, |acc, i| {
+            let src = pos
+                .iter()
+                .enumerate()
+                .fold(0, |src, (j, &p)| src | (((i >> p) & 1) << j));
+            acc | (((self.tt >> src) & 1) << i)
+        })
+    }
+
+    /// Cut of a 2-input gate from cuts of its inputs, if the leaves fit
+    fn merge(gate_type: GateType, a: &Cut, b: &Cut) -> Option<Cut> {
+        let mut leaves = [0; 3];
+        let mut len = 0;
+        for &w in a.leaves().iter().chain(b.leaves()) {
+            if !leaves[..len].contains(&w) {
+                if len == 3 {
+                    return None;
+                }
+                leaves[len] = w;
+                len += 1;
+            }
+        }
+        leaves[..len].sort_unstable();
+        let (ta, tb) = (a.expand(&leaves[..len]), b.expand(&leaves[..len]));
+        let tt = (0..8).fold(0, |acc, i| {
+            let bit = gate_type.eval(&[(ta >> i) & 1 == 1, (tb >> i) & 1 == 1]);
+            acc | (u8::from(bit) << i)
+        });
+        Some(Cut { leaves, len, tt })
+    }
+}
+
+fn and_count(ckt: &Circuit) -> usize {
+    ckt.gates
+        .iter()
+        .filter(|g| g.gate_type.is_and_like())
+        .count()
+}
+
+/// Emits the gates of a recipe on top of `leaves`, the last one driving `root`
+struct Emitter<'a> {
+    gates: &'a mut Vec<Gate>,
+    num_wires: &'a mut usize,
+}
+
+impl Emitter<'_> {
+    fn fresh(&mut self) -> WireId {
+        *self.num_wires += 1;
+        *self.num_wires - 1
+    }
+
+    /// XOR of the selected wires, into `out` if given
+    fn xor(&mut self, selected: &[WireId], negated: bool, out: Option<WireId>) -> WireId {
+        let Some((&last, rest)) = selected.split_last() else {
+            let out = out.unwrap_or_else(|| self.fresh());
+            self.push(GateType::Const(negated), vec![], out);
+            return out;
+        };
+        let Some((&first, middle)) = rest.split_first() else {
+            return match (negated, out) {
+                (false, None) => last,
+                (false, Some(out)) => self.push(GateType::Buf, vec![last], out),
+                (true, _) => {
+                    let out = out.unwrap_or_else(|| self.fresh());
+                    self.push(GateType::Inv, vec![last], out)
+                }
+            };
+        };
+        let mut acc = first;
+        for &w in middle {
+            let next = self.fresh();
+            acc = self.push(GateType::Xor, vec![acc, w], next);
+        }
+        let out = out.unwrap_or_else(|| self.fresh());
+        let gate_type = if negated {
+            GateType::Xnor
+        } else {
+            GateType::Xor
+        };
+        self.push(gate_type, vec![acc, last], out)
+    }
+
+    fn push(&mut self, gate_type: GateType, inputs: Vec<WireId>, output: WireId) -> WireId {
+        self.gates.push(Gate {
+            gate_type,
+            inputs,
+            output,
+        });
+        output
+    }
+
+    fn recipe(&mut self, recipe: &Recipe, cut: &Cut, root: WireId) {
+        // variables a cut doesn't depend on can take any value
+        let mut vars: Vec<WireId> = (0..3).map(|j| cut.leaves[j.min(cut.len - 1)]).collect();
+        let select = |l: Linear, vars: &[WireId]| -> Vec<WireId> {
+            (0..vars.len())
+                .filter(|j| (l.mask >> j) & 1 == 1)
+                .map(|j| vars[j])
+                .collect()
+        };
+        for &(a, b) in &recipe.ands {
+            // operand polarity goes into the truth table
+            let wa = self.xor(&select(a, &vars), false, None);
+            let wb = self.xor(&select(b, &vars), false, None);
+            let gate_type = match flip_inputs(0x8, a.negated, b.negated) {
+                0x8 => GateType::And,
+                tt => GateType::Lut(tt),
+            };
+            let out = self.fresh();
+            vars.push(self.push(gate_type, vec![wa, wb], out));
+        }
+        self.xor(&select(recipe.out, &vars), recipe.out.negated, Some(root));
+    }
+}
+
+impl Circuit {
+    /// Reduce the number of AND-like gates by rewriting cones of at most three inputs with
+    /// AND-minimal implementations, repeated until it stops helping. XOR gates are free, so the
+    /// result may have more of them. Assumes the gates are in evaluation order; the result is
+    /// simplified and renumbered like [`Circuit::simplify`].
+    pub fn reduce_and_gates(&self) -> anyhow::Result<Circuit> {
+        let mut best = self.simplify()?;
+        loop {
+            let next = best.rewrite_cuts().simplify()?;
+            if and_count(&next) >= and_count(&best) {
+                return Ok(best);
+            }
+            best = next;
+        }
+    }
+
+    /// One round of cut rewriting. Cones that are replaced are left for dead-gate elimination.
+    fn rewrite_cuts(&self) -> Circuit {
+        let recipes = recipes();
+        let mut driver: Vec<Option<usize>> = vec![None; self.num_wires];
+        let mut refs = vec![0u32; self.num_wires];
+        for (i, g) in self.gates.iter().enumerate() {
+            driver[g.output] = Some(i);
+            for &w in &g.inputs {
+                refs[w] += 1;
+            }
+        }
+        for &w in &self.outputs {
+            // outputs always stay
+            refs[w] += 1;
+        }
+
+        let mut cuts: Vec<Vec<Cut>> = (0..self.num_wires).map(|w| vec![Cut::trivial(w)]).collect();
+        for g in &self.gates {
+            let mut found = match (g.gate_type, g.inputs.as_slice()) {
+                (GateType::Const(v), _) => vec![Cut {
+                    leaves: [0; 3],
+                    len: 0,
+                    tt: if v { 0xFF } else { 0 },
+                }],
+                (gate_type, &[x]) => cuts[x]
+                    .iter()
+                    .map(|c| Cut {
+                        tt: if gate_type == GateType::Inv {
+                            !c.tt
+                        } else {
+                            c.tt
+                        },
+                        ..*c
+                    })
+                    .collect(),
+                (gate_type, &[a, b]) => {
+                    let mut found: Vec<Cut> = Vec::new();
+                    for ca in &cuts[a] {
+                        for cb in &cuts[b] {
+                            if let Some(c) = Cut::merge(gate_type, ca, cb)
+                                && !found.iter().any(|f| f.leaves() == c.leaves())
+                            {
+                                found.push(c);
+                            }
+                        }
+                    }
+                    found
+                }
+                _ => vec![],
+            };
+            found.sort_by_key(|c| c.len);
+            found.truncate(MAX_CUTS - 1);
+            found.insert(0, Cut::trivial(g.output));
+            cuts[g.output] = found;
+        }
+
+        // pick replacements from the outputs backwards, so that a cone that is replaced is not
+        // also used as the root of another replacement
+        let mut consumed = vec![false; self.num_wires];
+        let mut chosen: Vec<Option<Cut>> = vec![None; self.gates.len()];
+        for (i, g) in self.gates.iter().enumerate().rev() {
+            if consumed[g.output] || g.inputs.is_empty() {
+                continue;
+            }
+            let mut best: Option<(usize, Cut)> = None;
+            for cut in &cuts[g.output][1..] {
+                if cut.len == 0 {
+                    continue;
+                }
+                let cost = recipes[usize::from(cut.tt)].ands.len();
+                let freed = self.deref_cone(g.output, cut.leaves(), &driver, &mut refs, None);
+                if freed > cost && best.is_none_or(|(gain, _)| freed - cost > gain) {
+                    best = Some((freed - cost, *cut));
+                }
+            }
+            if let Some((_, cut)) = best {
+                self.deref_cone(
+                    g.output,
+                    cut.leaves(),
+                    &driver,
+                    &mut refs,
+                    Some(&mut consumed),
+                );
+                for &l in cut.leaves() {
+                    refs[l] += 1;
+                }
+                chosen[i] = Some(cut);
+            }
+        }
+
+        let mut num_wires = self.num_wires;
+        let mut gates = Vec::with_capacity(self.gates.len());
+        for (g, cut) in self.gates.iter().zip(chosen) {
+            match cut {
+                Some(cut) => Emitter {
+                    gates: &mut gates,
+                    num_wires: &mut num_wires,
+                }
+                .recipe(&recipes[usize::from(cut.tt)], &cut, g.output),
+                None => gates.push(g.clone()),
+            }
+        }
+
+        Circuit {
+            num_wires,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            input_groups: self.input_groups.clone(),
+            output_groups: self.output_groups.clone(),
+            gates,
+        }
+        .remove_dead_gates()
+    }
+
+    /// Count the AND-like gates that die if `root` is recomputed from `leaves`: the root and
+    /// every gate of its cone read only from inside the cone. With `consumed`, the cone is
+    /// marked there and the references are not restored.
+    fn deref_cone(
+        &self,
+        root: WireId,
+        leaves: &[WireId],
+        driver: &[Option<usize>],
+        refs: &mut [u32],
+        mut consumed: Option<&mut Vec<bool>>,
+    ) -> usize {
+        let mut freed = 0;
+        let mut touched = Vec::new();
+        let mut stack = vec![root];
+        while let Some(w) = stack.pop() {
+            let Some(gi) = driver[w] else { continue };
+            let g = &self.gates[gi];
+            if g.gate_type.is_and_like() {
+                freed += 1;
+            }
+            if let Some(consumed) = consumed.as_deref_mut() {
+                consumed[w] = true;
+            }
+            for &i in &g.inputs {
+                if leaves.contains(&i) {
+                    continue;
+                }
+                refs[i] -= 1;
+                touched.push(i);
+                if refs[i] == 0 {
+                    stack.push(i);
+                }
+            }
+        }
+        if consumed.is_none() {
+            for i in touched {
+                refs[i] += 1;
+            }
+        }
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{assert_equivalent, circuit, gate};
+
+    #[test]
+    fn recipes_implement_their_function() {
+        let recipes = recipes();
+        for (f, recipe) in recipes.iter().enumerate() {
+            let mut vars = VARS.to_vec();
+            for &(a, b) in &recipe.ands {
+                vars.push(a.eval(&vars) & b.eval(&vars));
+            }
+            assert_eq!(usize::from(recipe.out.eval(&vars)), f);
+        }
+        // majority needs a single AND, a 3-input AND needs two
+        assert_eq!(recipes[0xE8].ands.len(), 1);
+        assert_eq!(recipes[0x80].ands.len(), 2);
+        assert_eq!(recipes.iter().filter(|r| r.ands.is_empty()).count(), 16);
+    }
+
+    #[test]
+    fn majority_uses_one_and() {
+        // maj(a, b, c) = ab OR ac OR bc, naively with five AND-like gates
+        let ckt = circuit(
+            3,
+            vec![
+                gate(GateType::And, &[0, 1], 3),
+                gate(GateType::And, &[0, 2], 4),
+                gate(GateType::And, &[1, 2], 5),
+                gate(GateType::Or, &[3, 4], 6),
+                gate(GateType::Or, &[6, 5], 7),
+            ],
+            vec![7],
+        );
+        let reduced = ckt.reduce_and_gates().unwrap();
+        assert_equivalent(&ckt, &reduced);
+        assert_eq!(and_count(&reduced), 1);
+    }
+
+    #[test]
+    fn shared_gates_are_not_counted_as_freed() {
+        // a AND b is also an output, so rewriting the OR on top of it doesn't pay off
+        let ckt = circuit(
+            3,
+            vec![
+                gate(GateType::And, &[0, 1], 3),
+                gate(GateType::Or, &[3, 2], 4),
+            ],
+            vec![3, 4],
+        );
+        let reduced = ckt.reduce_and_gates().unwrap();
+        assert_equivalent(&ckt, &reduced);
+        assert_eq!(and_count(&reduced), 2);
+    }
+
+    #[test]
+    fn random_circuits_stay_equivalent_with_fewer_ands() {
+        for seed in 0..4 {
+            let mut ckt = Circuit::random(8, 300, 0.3, seed);
+            ckt.outputs.truncate(4);
+            ckt.output_groups = vec![4];
+            let reduced = ckt.reduce_and_gates().unwrap();
+            assert_equivalent(&ckt, &reduced);
+            assert!(and_count(&reduced) <= and_count(&ckt.simplify().unwrap()));
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Circuit transformations. Every pass returns a new circuit that computes the same function on
+//! the same primary inputs and outputs.
+
+mod fold;
+mod invert;
+mod mc;
+mod order;
+mod simplify;
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use std::path::PathBuf;
+
+    use crate::{Circuit, Gate, GateType, WireId};
+
+    /// `circuits/<name>/<name>.bristol`, one of the example circuits shipped with the repo
+    pub(crate) fn example_path(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "../../circuits", name]
+            .iter()
+            .collect::<PathBuf>()
+            .join(format!("{name}.bristol"))
+    }
+
+    pub(crate) fn gate(gate_type: GateType, inputs: &[WireId], output: WireId) -> Gate {
+        Gate {
+            gate_type,
+            inputs: inputs.to_vec(),
+            output,
+        }
+    }
+
+    /// A circuit reading the first `num_inputs` wires as one input value
+    pub(crate) fn circuit(num_inputs: usize, gates: Vec<Gate>, outputs: Vec<WireId>) -> Circuit {
+        let num_wires = gates
+            .iter()
+            .map(|g| g.output + 1)
+            .max()
+            .unwrap_or(num_inputs);
+        Circuit {
+            num_wires,
+            inputs: (0..num_inputs).collect(),
+            input_groups: vec![num_inputs],
+            output_groups: vec![outputs.len()],
+            outputs,
+            gates,
+        }
+    }
+
+    /// Check that both circuits agree on every input, for circuits with few inputs
+    pub(crate) fn assert_equivalent(a: &Circuit, b: &Circuit) {
+        let n = a.inputs.len();
+        assert_eq!(n, b.inputs.len());
+        assert!(n <= 16, "too many inputs for exhaustive check");
+        for x in 0..1u32 << n {
+            let bits: Vec<bool> = (0..n).map(|i| (x >> i) & 1 == 1).collect();
+            assert_eq!(
+                a.eval_bits(&bits).unwrap(),
+                b.eval_bits(&bits).unwrap(),
+                "differ on input {x:#b}"
+            );
+        }
+    }
+}
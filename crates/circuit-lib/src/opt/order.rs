@@ -0,0 +1,220 @@
+//! Gate scheduling: a topological order that keeps few wires live at once.
+//!
+//! The gates are emitted in depth-first post-order from the outputs, visiting the deeper input
+//! of a gate first (as Sethi-Ullman numbering does for expression trees), so a value is mostly
+//! computed right before it is read. Gates that no output depends on follow in their original
+//! order.
+
+use std::borrow::Cow;
+
+use crate::{Circuit, Gate, WireId};
+
+/// no driving gate: a primary input
+const NONE: usize = usize::MAX;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    Open,
+    Done,
+}
+
+/// Depth-first post-order over the gates, from `roots`
+fn post_order(
+    children: &[[usize; 2]],
+    roots: impl Iterator<Item = usize>,
+) -> anyhow::Result<Vec<usize>> {
+    let mut state = vec![Visit::New; children.len()];
+    let mut order = Vec::with_capacity(children.len());
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    for root in roots {
+        if state[root] != Visit::New {
+            continue;
+        }
+        state[root] = Visit::Open;
+        stack.push((root, 0));
+        while let Some((g, next)) = stack.last_mut() {
+            let g = *g;
+            match children[g].get(*next) {
+                Some(&c) => {
+                    *next += 1;
+                    if c == NONE {
+                        continue;
+                    }
+                    match state[c] {
+                        Visit::New => {
+                            state[c] = Visit::Open;
+                            stack.push((c, 0));
+                        }
+                        Visit::Open => anyhow::bail!("the circuit has a combinational loop"),
+                        Visit::Done => {}
+                    }
+                }
+                None => {
+                    state[g] = Visit::Done;
+                    order.push(g);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(order)
+}
+
+impl Circuit {
+    /// Whether every gate comes after the gates driving its inputs
+    pub fn is_in_evaluation_order(&self) -> bool {
+        self.check_evaluation_order().is_ok()
+    }
+
+    /// The circuit itself if its gates are in evaluation order, else [`Circuit::reorder`]ed
+    pub fn sorted(&self) -> anyhow::Result<Cow<'_, Circuit>> {
+        Ok(if self.is_in_evaluation_order() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.reorder()?)
+        })
+    }
+
+    /// Fail on the first gate that reads a wire that is neither an input nor driven by an
+    /// earlier gate
+    pub fn check_evaluation_order(&self) -> anyhow::Result<()> {
+        let mut ready = vec![false; self.num_wires];
+        for &w in &self.inputs {
+            ready[w] = true;
+        }
+        for g in &self.gates {
+            if let Some(w) = g.inputs.iter().find(|&&w| !ready[w]) {
+                anyhow::bail!(
+                    "the gate driving wire {} reads wire {w} before any gate drives it, reorder \
+                     the circuit first",
+                    g.output
+                );
+            }
+            ready[g.output] = true;
+        }
+        Ok(())
+    }
+
+    /// Put the gates in an evaluation order that keeps few wires live, whatever their order
+    /// in the file, and renumber the wires densely (see [`Circuit::with_standard_layout`]).
+    /// Fails on combinational loops and on wires that are read but never driven.
+    pub fn reorder(&self) -> anyhow::Result<Circuit> {
+        let mut driver = vec![NONE; self.num_wires];
+        let mut is_input = vec![false; self.num_wires];
+        for &w in &self.inputs {
+            is_input[w] = true;
+        }
+        for (i, g) in self.gates.iter().enumerate() {
+            anyhow::ensure!(
+                driver[g.output] == NONE && !is_input[g.output],
+                "wire {} is an input or driven more than once",
+                g.output
+            );
+            driver[g.output] = i;
+        }
+
+        let mut children = Vec::with_capacity(self.gates.len());
+        for g in &self.gates {
+            anyhow::ensure!(
+                g.inputs.len() <= 2,
+                "{} gate with more than 2 inputs",
+                g.gate_type
+            );
+            let mut c = [NONE; 2];
+            for (slot, &w) in c.iter_mut().zip(&g.inputs) {
+                anyhow::ensure!(
+                    is_input[w] || driver[w] != NONE,
+                    "wire {w} is read but never driven"
+                );
+                *slot = driver[w];
+            }
+            children.push(c);
+        }
+
+        // a first pass for the depths, a second one visiting the deeper input first
+        let roots = || {
+            let outputs = self.outputs.iter().map(|&w: &WireId| driver[w]);
+            outputs
+                .filter(|&g| g != NONE)
+                .chain(0..self.gates.len())
+                .collect::<Vec<_>>()
+        };
+        let mut depth = vec![0usize; self.gates.len()];
+        for g in post_order(&children, roots().into_iter())? {
+            depth[g] = 1 + children[g]
+                .iter()
+                .filter(|&&c| c != NONE)
+                .map(|&c| depth[c])
+                .max()
+                .unwrap_or(0);
+        }
+        for c in &mut children {
+            let d = |g: usize| if g == NONE { 0 } else { depth[g] };
+            if d(c[1]) > d(c[0]) {
+                c.swap(0, 1);
+            }
+        }
+        let order = post_order(&children, roots().into_iter())?;
+
+        let gates: Vec<Gate> = order.into_iter().map(|g| self.gates[g].clone()).collect();
+        Circuit {
+            gates,
+            ..self.clone_io()
+        }
+        .with_standard_layout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_util::{assert_equivalent, circuit, gate};
+    use crate::{Circuit, GateType};
+    use rand::{SeedableRng, seq::SliceRandom};
+    use rand_chacha::ChaCha12Rng;
+
+    #[test]
+    fn sorts_shuffled_gates() {
+        let ckt = Circuit::random(12, 500, 0.5, 8);
+        let mut shuffled = ckt.clone();
+        shuffled.gates.shuffle(&mut ChaCha12Rng::seed_from_u64(1));
+        assert!(!shuffled.is_in_evaluation_order());
+
+        let sorted = shuffled.reorder().unwrap();
+        assert!(sorted.is_in_evaluation_order());
+        assert!(sorted.has_standard_layout());
+        assert_eq!(sorted.gates.len(), ckt.gates.len());
+        assert_equivalent(&ckt, &sorted);
+
+        let looped = circuit(
+            1,
+            vec![
+                gate(GateType::And, &[0, 2], 1),
+                gate(GateType::Xor, &[1, 0], 2),
+            ],
+            vec![2],
+        );
+        assert!(looped.reorder().is_err());
+    }
+
+    #[test]
+    fn computes_values_right_before_they_are_read() {
+        // x_i = in_(i % 4) AND in_((i + 1) % 4), all computed before the XOR chain reads them
+        let n = 100;
+        let mut gates: Vec<_> = (0..n)
+            .map(|i| gate(GateType::And, &[i % 4, (i + 1) % 4], 4 + i))
+            .collect();
+        let mut acc = 4;
+        for i in 1..n {
+            gates.push(gate(GateType::Xor, &[acc, 4 + i], 4 + n + i));
+            acc = 4 + n + i;
+        }
+        let ckt = circuit(4, gates, vec![acc]);
+        assert!(ckt.stats().unwrap().peak_live_wires > n);
+
+        let sorted = ckt.reorder().unwrap();
+        assert!(sorted.stats().unwrap().peak_live_wires <= 8);
+        assert_eq!(sorted.num_wires, ckt.num_wires - 1);
+        assert_equivalent(&ckt, &sorted);
+    }
+}
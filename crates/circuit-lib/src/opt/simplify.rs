@@ -0,0 +1,294 @@
+//! Dead-gate elimination, structural hashing and the combined simplification pipeline.
+
+use std::collections::HashMap;
+
+use crate::{Circuit, Gate, GateType, WireId};
+
+/// Structural identity of a gate: two gates with the same key compute the same wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Const(bool),
+    Unary(GateType, WireId),
+    /// truth table over inputs sorted by wire id, so `AND(a, b)`, `AND(b, a)` and `LUT8(a, b)`
+    /// all agree
+    Binary(u8, WireId, WireId),
+}
+
+impl Key {
+    fn of(gate_type: GateType, inputs: &[WireId]) -> Key {
+        match (gate_type.truth_table(), inputs) {
+            (Some(tt), &[a, b]) if a > b => {
+                // swapping the inputs swaps rows 1 (a=0, b=1) and 2 (a=1, b=0)
+                let tt = (tt & 0x9) | ((tt & 0x2) << 1) | ((tt & 0x4) >> 1);
+                Key::Binary(tt, b, a)
+            }
+            (Some(tt), &[a, b]) => Key::Binary(tt, a, b),
+            (_, &[a]) => Key::Unary(gate_type, a),
+            _ => match gate_type {
+                GateType::Const(v) => Key::Const(v),
+                _ => unreachable!("{gate_type} gate with {} inputs", inputs.len()),
+            },
+        }
+    }
+}
+
+impl Circuit {
+    /// Remove gates whose output never reaches a primary output. Wire ids are kept.
+    pub fn remove_dead_gates(&self) -> Circuit {
+        let mut live = vec![false; self.num_wires];
+        for &w in &self.outputs {
+            live[w] = true;
+        }
+
+        let mut gates: Vec<Gate> = Vec::with_capacity(self.gates.len());
+        for g in self.gates.iter().rev() {
+            if live[g.output] {
+                for &w in &g.inputs {
+                    live[w] = true;
+                }
+                gates.push(g.clone());
+            }
+        }
+        gates.reverse();
+
+        Circuit {
+            gates,
+            ..self.clone_io()
+        }
+    }
+
+    /// Merge gates that compute the same function of the same wires (structural hashing),
+    /// assuming the gates are in evaluation order. Readers of a duplicate are rewired to the
+    /// first copy; a duplicate that drives a primary output becomes an `EQW` of the first copy.
+    pub fn merge_duplicate_gates(&self) -> Circuit {
+        let mut alias: Vec<WireId> = (0..self.num_wires).collect();
+        let mut seen: HashMap<Key, WireId> = HashMap::with_capacity(self.gates.len());
+        let mut gates = Vec::with_capacity(self.gates.len());
+
+        for g in &self.gates {
+            let inputs: Vec<WireId> = g.inputs.iter().map(|&w| alias[w]).collect();
+            match seen.get(&Key::of(g.gate_type, &inputs)) {
+                Some(&first) => alias[g.output] = first,
+                None => {
+                    seen.insert(Key::of(g.gate_type, &inputs), g.output);
+                    gates.push(Gate {
+                        gate_type: g.gate_type,
+                        inputs,
+                        output: g.output,
+                    });
+                }
+            }
+        }
+
+        for &w in &self.outputs {
+            if alias[w] != w {
+                gates.push(Gate {
+                    gate_type: GateType::Buf,
+                    inputs: vec![alias[w]],
+                    output: w,
+                });
+            }
+        }
+
+        Circuit {
+            gates,
+            ..self.clone_io()
+        }
+    }
+
+    /// Fold constants and merge duplicate gates until neither finds anything more, then drop
+    /// dead gates and renumber the remaining wires densely (see
+    /// [`Circuit::with_standard_layout`]).
+    pub fn simplify(&self) -> anyhow::Result<Circuit> {
+        let mut ckt = self.fold_constants().merge_duplicate_gates();
+        loop {
+            // merging can expose new folds (`x XOR x'` with x' a copy of x) and vice versa
+            let next = ckt.fold_constants().merge_duplicate_gates();
+            if next.gates.len() >= ckt.gates.len() {
+                break;
+            }
+            ckt = next;
+        }
+        ckt.remove_dead_gates()
+            .inline_output_copies()
+            .with_standard_layout()
+    }
+
+    /// The circuit as the garbler takes it: gates in evaluation order (see
+    /// [`Circuit::reorder`], only applied if they aren't), constants folded, inverters absorbed
+    /// and wires in the standard layout. Each pass drops its input, so a large circuit is held
+    /// at most twice.
+    ///
+    /// The circuit hash of a text circuit is taken over this form, so a change to the circuits
+    /// it produces must bump [`crate::binary::CIRCUIT_DOMAIN`].
+    pub fn into_garbling_form(self) -> anyhow::Result<Circuit> {
+        let sorted = if self.is_in_evaluation_order() {
+            self
+        } else {
+            self.reorder()?
+        };
+        let folded = sorted.fold_constants();
+        drop(sorted);
+        let absorbed = folded.absorb_inverters();
+        drop(folded);
+        absorbed.with_standard_layout()
+    }
+
+    /// Let the gate behind an output `EQW` drive the output wire itself, when the copied wire is
+    /// neither an input nor an output.
+    fn inline_output_copies(&self) -> Circuit {
+        let mut is_io = vec![false; self.num_wires];
+        let mut is_output = vec![false; self.num_wires];
+        for &w in &self.inputs {
+            is_io[w] = true;
+        }
+        for &w in &self.outputs {
+            is_io[w] = true;
+            is_output[w] = true;
+        }
+
+        let mut rename: Vec<WireId> = (0..self.num_wires).collect();
+        let mut inlined = vec![false; self.gates.len()];
+        for (i, g) in self.gates.iter().enumerate() {
+            if g.gate_type == GateType::Buf && is_output[g.output] {
+                let src = g.inputs[0];
+                // only the first copy of a wire can take it over
+                if !is_io[src] && rename[src] == src {
+                    rename[src] = g.output;
+                    inlined[i] = true;
+                }
+            }
+        }
+
+        let gates = self
+            .gates
+            .iter()
+            .zip(inlined)
+            .filter(|(_, inlined)| !inlined)
+            .map(|(g, _)| Gate {
+                gate_type: g.gate_type,
+                inputs: g.inputs.iter().map(|&w| rename[w]).collect(),
+                output: rename[g.output],
+            })
+            .collect();
+
+        Circuit {
+            gates,
+            ..self.clone_io()
+        }
+    }
+
+    /// Same wires and I/O, no gates
+    pub(super) fn clone_io(&self) -> Circuit {
+        Circuit {
+            num_wires: self.num_wires,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            input_groups: self.input_groups.clone(),
+            output_groups: self.output_groups.clone(),
+            gates: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{assert_equivalent, circuit, gate};
+
+    #[test]
+    fn dead_gates_are_removed() {
+        let ckt = circuit(
+            2,
+            vec![
+                gate(GateType::And, &[0, 1], 2),
+                gate(GateType::Xor, &[0, 2], 3), // only read by the dead gate 5
+                gate(GateType::Xor, &[0, 1], 4),
+                gate(GateType::And, &[3, 1], 5),
+                gate(GateType::Or, &[2, 4], 6),
+            ],
+            vec![6],
+        );
+        let live = ckt.remove_dead_gates();
+        assert_eq!(
+            live.gates,
+            vec![
+                ckt.gates[0].clone(),
+                ckt.gates[2].clone(),
+                ckt.gates[4].clone()
+            ]
+        );
+        assert_equivalent(&ckt, &live);
+    }
+
+    #[test]
+    fn duplicates_are_merged_up_to_input_order() {
+        let ckt = circuit(
+            2,
+            vec![
+                gate(GateType::And, &[0, 1], 2),
+                gate(GateType::And, &[1, 0], 3),
+                gate(GateType::Lut(0x8), &[0, 1], 4),
+                // NOT a AND b written both ways round
+                gate(GateType::Lut(0x2), &[3, 4], 5),
+                gate(GateType::Lut(0x2), &[0, 1], 6),
+                gate(GateType::Lut(0x4), &[1, 0], 7),
+                gate(GateType::Xor, &[6, 7], 8),
+            ],
+            vec![5, 8, 4],
+        );
+        let merged = ckt.merge_duplicate_gates();
+        assert_equivalent(&ckt, &merged);
+        assert_eq!(
+            merged.gates,
+            vec![
+                gate(GateType::And, &[0, 1], 2),
+                gate(GateType::Lut(0x2), &[2, 2], 5),
+                gate(GateType::Lut(0x2), &[0, 1], 6),
+                gate(GateType::Xor, &[6, 6], 8),
+                gate(GateType::Buf, &[2], 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn simplify_renumbers_densely() {
+        let ckt = circuit(
+            3,
+            vec![
+                gate(GateType::Const(true), &[], 5),
+                gate(GateType::And, &[0, 5], 7), // a
+                gate(GateType::And, &[7, 1], 9),
+                gate(GateType::And, &[1, 0], 10),
+                gate(GateType::Xor, &[9, 10], 12), // 0
+                gate(GateType::Nor, &[2, 2], 13),  // NOT c
+                gate(GateType::Or, &[12, 13], 14),
+                gate(GateType::And, &[2, 1], 15), // dead
+            ],
+            vec![14, 10],
+        );
+        let simple = ckt.simplify().unwrap();
+        assert_equivalent(&ckt, &simple);
+        assert!(simple.has_standard_layout());
+        assert_eq!(simple.num_wires, 5);
+        assert_eq!(
+            simple.gates,
+            vec![
+                gate(GateType::And, &[0, 1], 4),
+                gate(GateType::Inv, &[2], 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn simplify_keeps_random_circuits_equivalent() {
+        for seed in 0..4 {
+            let mut ckt = Circuit::random(8, 400, 0.6, seed);
+            ckt.outputs.truncate(3);
+            ckt.output_groups = vec![3];
+            let simple = ckt.simplify().unwrap();
+            assert!(simple.get_gate_count() <= ckt.get_gate_count());
+            assert_equivalent(&ckt, &simple);
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! Seedable random circuit generators used for benchmarking.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+
+use crate::{Circuit, Gate, GateType, WireId, io_from_topology};
+
+/// How gates are arranged into layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// every gate may read any earlier wire, depth is whatever falls out
+    Unlayered,
+    /// gates are spread over this many layers
+    Depth(usize),
+    /// layers hold this many gates each
+    Width(usize),
+}
+
+/// How gate inputs are picked among the wires that are available to them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FanOut {
+    /// every available wire is equally likely, so early wires collect a large fan-out
+    Uniform,
+    /// with this probability, pick a wire that hasn't been read yet; keeps fan-out close to 1
+    /// and leaves few dangling wires
+    PreferUnused(f64),
+    /// only the most recent `n` available wires are picked, giving local circuits
+    Window(usize),
+}
+
+impl FromStr for FanOut {
+    type Err = anyhow::Error;
+
+    /// Parses `uniform`, `unused:<probability>` or `window:<n>`
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (kind, arg) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "uniform" => Ok(FanOut::Uniform),
+            "unused" => Ok(FanOut::PreferUnused(
+                arg.parse().context("expected `unused:<probability>`")?,
+            )),
+            "window" => Ok(FanOut::Window(
+                arg.parse().context("expected `window:<n>`")?,
+            )),
+            other => anyhow::bail!("unknown fan-out distribution `{other}`"),
+        }
+    }
+}
+
+/// Knobs for [`Circuit::random_with`]. Start from [`RandomCircuitConfig::new`] and override
+/// what you need.
+#[derive(Debug, Clone)]
+pub struct RandomCircuitConfig {
+    /// number of primary input wires
+    pub num_inputs: usize,
+    /// how many of the inputs belong to the garbler; the rest are the evaluator's.
+    /// `None` declares a single input value
+    pub garbler_inputs: Option<usize>,
+    /// number of gates
+    pub num_gates: usize,
+    /// fraction of XOR gates
+    pub xor_ratio: f64,
+    /// fraction of INV gates; the remaining gates are AND
+    pub inv_ratio: f64,
+    /// layering of the gates
+    pub shape: Shape,
+    /// number of output wires, taken from the last gates. `None` makes every gate output that
+    /// is never read an output
+    pub num_outputs: Option<usize>,
+    /// distribution used to pick gate inputs
+    pub fan_out: FanOut,
+    /// seed of the generator
+    pub seed: u64,
+}
+
+impl RandomCircuitConfig {
+    /// Unlayered AND-only circuit with uniform input selection and seed 0.
+    pub fn new(num_inputs: usize, num_gates: usize) -> Self {
+        RandomCircuitConfig {
+            num_inputs,
+            garbler_inputs: None,
+            num_gates,
+            xor_ratio: 0.0,
+            inv_ratio: 0.0,
+            shape: Shape::Unlayered,
+            num_outputs: None,
+            fan_out: FanOut::Uniform,
+            seed: 0,
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.num_inputs > 0 || self.num_gates == 0,
+            "gates need at least one input wire"
+        );
+        anyhow::ensure!(
+            self.garbler_inputs.is_none_or(|g| g <= self.num_inputs),
+            "more garbler inputs than inputs"
+        );
+        anyhow::ensure!(
+            self.xor_ratio >= 0.0
+                && self.inv_ratio >= 0.0
+                && self.xor_ratio + self.inv_ratio <= 1.0,
+            "XOR and INV ratios must be non-negative and add up to at most 1"
+        );
+        anyhow::ensure!(
+            self.num_outputs.is_none_or(|n| n <= self.num_gates),
+            "more outputs than gates"
+        );
+        match self.shape {
+            Shape::Depth(0) | Shape::Width(0) => anyhow::bail!("depth and width must be positive"),
+            Shape::Depth(d) => anyhow::ensure!(d <= self.num_gates, "depth exceeds gate count"),
+            _ => {}
+        }
+        match self.fan_out {
+            FanOut::PreferUnused(p) => {
+                anyhow::ensure!((0.0..=1.0).contains(&p), "probability must be within 0..=1")
+            }
+            FanOut::Window(0) => anyhow::bail!("window must be positive"),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Wires that have not been read by any gate yet, with O(1) removal.
+struct UnusedPool {
+    wires: Vec<WireId>,
+    // position of each wire in `wires`, usize::MAX once it is used
+    pos: Vec<usize>,
+}
+
+impl UnusedPool {
+    fn new(num_wires: usize) -> Self {
+        UnusedPool {
+            wires: Vec::new(),
+            pos: vec![usize::MAX; num_wires],
+        }
+    }
+
+    fn push(&mut self, w: WireId) {
+        self.pos[w] = self.wires.len();
+        self.wires.push(w);
+    }
+
+    fn remove(&mut self, w: WireId) {
+        let p = self.pos.get(w).copied().unwrap_or(usize::MAX);
+        if p == usize::MAX {
+            return;
+        }
+        self.wires.swap_remove(p);
+        if let Some(&moved) = self.wires.get(p) {
+            self.pos[moved] = p;
+        }
+        self.pos[w] = usize::MAX;
+    }
+}
+
+/// Picks one wire among `range` according to `fan_out`, preferring unused wires when asked.
+fn pick(
+    rng: &mut ChaCha12Rng,
+    range: std::ops::Range<WireId>,
+    fan_out: FanOut,
+    unused: &mut UnusedPool,
+) -> WireId {
+    let w = match fan_out {
+        FanOut::Uniform => rng.random_range(range),
+        FanOut::Window(n) => {
+            rng.random_range(range.end.saturating_sub(n).max(range.start)..range.end)
+        }
+        FanOut::PreferUnused(p) => {
+            // the pool only ever holds wires below `range.end`, but may hold some below
+            // `range.start` as well; fall back to a uniform pick if none qualify
+            let candidates = unused.wires.len();
+            let idx = (candidates > 0 && rng.random_bool(p))
+                .then(|| rng.random_range(0..candidates))
+                .map(|i| unused.wires[i])
+                .filter(|w| range.contains(w));
+            idx.unwrap_or_else(|| rng.random_range(range))
+        }
+    };
+    unused.remove(w);
+    w
+}
+
+impl Circuit {
+    /// Generate a random Boolean circuit with `num_inputs` input wires and `num_gates` gates.
+    /// The same `seed` always produces the same circuit.
+    pub fn random(num_inputs: usize, num_gates: usize, ratio_xor_to_and: f64, seed: u64) -> Self {
+        let config = RandomCircuitConfig {
+            xor_ratio: ratio_xor_to_and.clamp(0.0, 1.0),
+            seed,
+            ..RandomCircuitConfig::new(num_inputs, num_gates)
+        };
+        Self::random_with(&config).expect("default generator settings are valid")
+    }
+
+    /// Generate a random Boolean circuit as described by `config`. The same config always
+    /// produces the same circuit.
+    pub fn random_with(config: &RandomCircuitConfig) -> anyhow::Result<Self> {
+        config.validate()?;
+
+        let mut rng = ChaCha12Rng::seed_from_u64(config.seed);
+        let num_inputs = config.num_inputs;
+        let num_gates = config.num_gates;
+
+        // total number of wires is equal to sum of input wires and number of gates, assuming each gate has fan-out of 1
+        let num_wires = num_inputs + num_gates;
+
+        let width = match config.shape {
+            Shape::Unlayered => None,
+            Shape::Depth(d) => Some(num_gates.div_ceil(d)),
+            Shape::Width(w) => Some(w),
+        };
+
+        // only track unused wires when they are needed, the pool costs a word per wire
+        let prefer_unused = matches!(config.fan_out, FanOut::PreferUnused(_));
+        let mut unused = UnusedPool::new(if prefer_unused { num_wires } else { 0 });
+        // outputs of the current layer, which join the pool once the layer is complete
+        let mut pending: Vec<WireId> = if prefer_unused {
+            (0..num_inputs).collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut gates = Vec::with_capacity(num_gates);
+        // wires below `layer_start` are visible to the current layer, those in
+        // `prev_layer_start..layer_start` form the previous layer
+        let mut prev_layer_start = 0;
+        let mut layer_start = num_inputs;
+
+        for (g, out) in (num_inputs..num_wires).enumerate() {
+            match width {
+                Some(w) if g > 0 && g % w == 0 => {
+                    prev_layer_start = layer_start;
+                    layer_start = out;
+                }
+                Some(_) => {}
+                None => layer_start = out,
+            }
+            if layer_start == out {
+                pending.drain(..).for_each(|w| unused.push(w));
+            }
+
+            let x: f64 = rng.random();
+            let gate_type = if x < config.xor_ratio {
+                GateType::Xor
+            } else if x < config.xor_ratio + config.inv_ratio {
+                GateType::Inv
+            } else {
+                GateType::And
+            };
+
+            // in a layered circuit the first input comes from the previous layer, which pins
+            // the gate to its layer
+            let first_range = match width {
+                Some(_) => prev_layer_start..layer_start,
+                None => 0..layer_start,
+            };
+            let in0 = pick(&mut rng, first_range, config.fan_out, &mut unused);
+            let inputs = match gate_type {
+                GateType::Inv => vec![in0],
+                _ => vec![
+                    in0,
+                    pick(&mut rng, 0..layer_start, config.fan_out, &mut unused),
+                ],
+            };
+
+            gates.push(Gate {
+                gate_type,
+                inputs,
+                output: out,
+            });
+
+            if prefer_unused {
+                pending.push(out);
+            }
+        }
+
+        let (inputs, outputs) = match config.num_outputs {
+            Some(n) => (
+                (0..num_inputs).collect(),
+                (num_wires - n..num_wires).collect(),
+            ),
+            None => io_from_topology(num_wires, &gates),
+        };
+        let input_groups = match config.garbler_inputs {
+            Some(g) => vec![g, num_inputs - g],
+            None => vec![inputs.len()],
+        };
+
+        Ok(Circuit {
+            num_wires,
+            input_groups,
+            output_groups: vec![outputs.len()],
+            inputs,
+            outputs,
+            gates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // depth of every wire, counting all gates
+    fn depth(ckt: &Circuit) -> usize {
+        let mut level = vec![0; ckt.num_wires];
+        for g in &ckt.gates {
+            level[g.output] = 1 + g.inputs.iter().map(|&w| level[w]).max().unwrap();
+        }
+        level.into_iter().max().unwrap_or(0)
+    }
+
+    #[test]
+    fn default_config_matches_random() {
+        let config = RandomCircuitConfig {
+            xor_ratio: 0.6,
+            seed: 9,
+            ..RandomCircuitConfig::new(10, 300)
+        };
+        let a = Circuit::random_with(&config).unwrap();
+        let b = Circuit::random(10, 300, 0.6, 9);
+        assert_eq!(a.gates, b.gates);
+    }
+
+    #[test]
+    fn depth_and_width_are_respected() {
+        let config = RandomCircuitConfig {
+            xor_ratio: 0.5,
+            inv_ratio: 0.1,
+            shape: Shape::Depth(20),
+            ..RandomCircuitConfig::new(16, 1000)
+        };
+        assert_eq!(depth(&Circuit::random_with(&config).unwrap()), 20);
+
+        let config = RandomCircuitConfig {
+            shape: Shape::Width(10),
+            ..config
+        };
+        assert_eq!(depth(&Circuit::random_with(&config).unwrap()), 100);
+    }
+
+    #[test]
+    fn outputs_inputs_and_gate_mix() {
+        let config = RandomCircuitConfig {
+            garbler_inputs: Some(6),
+            xor_ratio: 0.4,
+            inv_ratio: 0.3,
+            num_outputs: Some(8),
+            fan_out: FanOut::PreferUnused(0.9),
+            seed: 3,
+            ..RandomCircuitConfig::new(10, 2000)
+        };
+        let ckt = Circuit::random_with(&config).unwrap();
+        assert_eq!(ckt.input_groups, vec![6, 4]);
+        assert_eq!(ckt.outputs.len(), 8);
+        assert!(ckt.has_standard_layout());
+
+        let invs = ckt
+            .gates
+            .iter()
+            .filter(|g| g.gate_type == GateType::Inv)
+            .count();
+        assert!((500..700).contains(&invs), "{invs} INV gates");
+        assert!(
+            ckt.gates
+                .iter()
+                .all(|g| g.inputs.len() == g.gate_type.arity())
+        );
+    }
+
+    #[test]
+    fn window_keeps_inputs_local() {
+        let config = RandomCircuitConfig {
+            fan_out: FanOut::Window(4),
+            ..RandomCircuitConfig::new(4, 500)
+        };
+        let ckt = Circuit::random_with(&config).unwrap();
+        assert!(
+            ckt.gates
+                .iter()
+                .all(|g| g.inputs.iter().all(|&w| g.output - w <= 4))
+        );
+    }
+
+    #[test]
+    fn fan_out_from_str() {
+        assert_eq!("uniform".parse::<FanOut>().unwrap(), FanOut::Uniform);
+        assert_eq!(
+            "unused:0.5".parse::<FanOut>().unwrap(),
+            FanOut::PreferUnused(0.5)
+        );
+        assert_eq!("window:8".parse::<FanOut>().unwrap(), FanOut::Window(8));
+        assert!("window".parse::<FanOut>().is_err());
+    }
+}
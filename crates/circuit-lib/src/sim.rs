@@ -0,0 +1,391 @@
+//! Plaintext evaluation of a circuit, the reference for what a garbled circuit must compute.
+//!
+//! Input and output values are bit vectors in wire order. When a value is read or printed as a
+//! number, its first wire is the least significant bit, as in the Bristol Fashion arithmetic
+//! circuits.
+//!
+//! [`Circuit::eval_packed`] evaluates 64 or 128 inputs per pass, one machine word per wire with
+//! a bit per input ("lane"), for randomized tests of large circuits.
+
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Not},
+    str::FromStr,
+};
+
+use anyhow::Context;
+
+use crate::{Circuit, GateType};
+
+/// A word of independent simulation lanes, bit `i` belonging to the `i`-th input vector
+pub trait Lanes:
+    Copy
+    + Eq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+    const ZERO: Self;
+    const LANES: usize;
+
+    fn lane(self, i: usize) -> bool;
+    fn with_lane(self, i: usize, bit: bool) -> Self;
+}
+
+macro_rules! impl_lanes {
+    ($($t:ty),*) => {$(
+        impl Lanes for $t {
+            const ZERO: Self = 0;
+            const LANES: usize = <$t>::BITS as usize;
+
+            fn lane(self, i: usize) -> bool {
+                (self >> i) & 1 == 1
+            }
+
+            fn with_lane(self, i: usize, bit: bool) -> Self {
+                (self & !(1 << i)) | (<$t>::from(bit) << i)
+            }
+        }
+    )*};
+}
+
+impl_lanes!(u64, u128);
+
+impl GateType {
+    /// [`GateType::eval`] on every lane at once
+    pub fn eval_lanes<L: Lanes>(self, inputs: &[L]) -> L {
+        match self {
+            GateType::And => inputs[0] & inputs[1],
+            GateType::Xor => inputs[0] ^ inputs[1],
+            GateType::Inv => !inputs[0],
+            GateType::Buf => inputs[0],
+            GateType::Const(false) => L::ZERO,
+            GateType::Const(true) => !L::ZERO,
+            _ => {
+                let tt = self
+                    .truth_table()
+                    .expect("2-input gates have a truth table");
+                let (a, b) = (inputs[0], inputs[1]);
+                // one minterm per row `2a+b` of the truth table
+                [!a & !b, !a & b, a & !b, a & b]
+                    .into_iter()
+                    .enumerate()
+                    .filter(|&(row, _)| (tt >> row) & 1 == 1)
+                    .fold(L::ZERO, |acc, (_, m)| acc | m)
+            }
+        }
+    }
+}
+
+impl Circuit {
+    /// Evaluate the circuit on all input wires at once and return all output wires. Fails if
+    /// the gates aren't in evaluation order.
+    pub fn eval_bits(&self, inputs: &[bool]) -> anyhow::Result<Vec<bool>> {
+        anyhow::ensure!(
+            inputs.len() == self.inputs.len(),
+            "expected {} input bits, got {}",
+            self.inputs.len(),
+            inputs.len()
+        );
+        self.check_evaluation_order()?;
+        let mut wires = vec![false; self.num_wires];
+        for (&w, &bit) in self.inputs.iter().zip(inputs) {
+            wires[w] = bit;
+        }
+        let mut bits = Vec::with_capacity(2);
+        for g in &self.gates {
+            bits.clear();
+            bits.extend(g.inputs.iter().map(|&w| wires[w]));
+            wires[g.output] = g.gate_type.eval(&bits);
+        }
+        Ok(self.outputs.iter().map(|&w| wires[w]).collect())
+    }
+
+    /// Evaluate the circuit on `L::LANES` inputs at once, given one word per input wire, and
+    /// return one word per output wire. Fails if the gates aren't in evaluation order.
+    pub fn eval_packed<L: Lanes>(&self, inputs: &[L]) -> anyhow::Result<Vec<L>> {
+        anyhow::ensure!(
+            inputs.len() == self.inputs.len(),
+            "expected {} input words, got {}",
+            self.inputs.len(),
+            inputs.len()
+        );
+        self.check_evaluation_order()?;
+        let mut wires = vec![L::ZERO; self.num_wires];
+        for (&w, &word) in self.inputs.iter().zip(inputs) {
+            wires[w] = word;
+        }
+        let mut words = Vec::with_capacity(2);
+        for g in &self.gates {
+            words.clear();
+            words.extend(g.inputs.iter().map(|&w| wires[w]));
+            wires[g.output] = g.gate_type.eval_lanes(&words);
+        }
+        Ok(self.outputs.iter().map(|&w| wires[w]).collect())
+    }
+
+    /// [`Circuit::eval_bits`] on many inputs, 128 per pass
+    pub fn eval_bits_batch(&self, inputs: &[Vec<bool>]) -> anyhow::Result<Vec<Vec<bool>>> {
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for chunk in inputs.chunks(u128::LANES) {
+            let mut words = vec![0u128; self.inputs.len()];
+            for (lane, bits) in chunk.iter().enumerate() {
+                anyhow::ensure!(
+                    bits.len() == self.inputs.len(),
+                    "expected {} input bits, got {}",
+                    self.inputs.len(),
+                    bits.len()
+                );
+                for (word, &bit) in words.iter_mut().zip(bits) {
+                    *word = word.with_lane(lane, bit);
+                }
+            }
+            let out = self.eval_packed(&words)?;
+            outputs.extend(
+                (0..chunk.len()).map(|lane| out.iter().map(|word| word.lane(lane)).collect()),
+            );
+        }
+        Ok(outputs)
+    }
+
+    /// Evaluate the circuit on one bit vector per input value (see `input_groups`) and return
+    /// one bit vector per output value.
+    pub fn eval(&self, inputs: &[Vec<bool>]) -> anyhow::Result<Vec<Vec<bool>>> {
+        anyhow::ensure!(
+            inputs.len() == self.input_groups.len(),
+            "expected {} input values, got {}",
+            self.input_groups.len(),
+            inputs.len()
+        );
+        for (i, (value, &width)) in inputs.iter().zip(&self.input_groups).enumerate() {
+            anyhow::ensure!(
+                value.len() == width,
+                "input value {i} has {} bits, expected {width}",
+                value.len()
+            );
+        }
+        let outputs = self.eval_bits(&inputs.concat())?;
+        anyhow::ensure!(
+            self.output_groups.iter().sum::<usize>() == outputs.len(),
+            "output groups don't add up to the number of outputs"
+        );
+        let mut rest = outputs.as_slice();
+        Ok(self
+            .output_groups
+            .iter()
+            .map(|&n| {
+                let (value, tail) = rest.split_at(n);
+                rest = tail;
+                value.to_vec()
+            })
+            .collect())
+    }
+}
+
+/// Read a `width`-bit value written as a decimal, `0x` hexadecimal or `0b` binary number.
+/// Fails if the number doesn't fit.
+pub fn parse_value(s: &str, width: usize) -> anyhow::Result<Vec<bool>> {
+    let (digits, radix) = if let Some(hex) = s.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        (bin, 2)
+    } else {
+        (s, 10)
+    };
+    let digits = digits.replace('_', "");
+    anyhow::ensure!(!digits.is_empty(), "empty value `{s}`");
+
+    if radix == 10 {
+        let n: u128 = digits
+            .parse()
+            .with_context(|| format!("invalid value `{s}`"))?;
+        anyhow::ensure!(
+            width >= 128 || n >> width == 0,
+            "{s} doesn't fit in {width} bits"
+        );
+        return Ok((0..width).map(|i| i < 128 && (n >> i) & 1 == 1).collect());
+    }
+
+    // hex and binary can be arbitrarily long: read digit by digit from the least significant
+    let step = if radix == 16 { 4 } else { 1 };
+    let mut bits = vec![false; width];
+    for (k, c) in digits.chars().rev().enumerate() {
+        let d = c
+            .to_digit(radix)
+            .with_context(|| format!("invalid digit `{c}` in `{s}`"))?;
+        for j in 0..step {
+            if (d >> j) & 1 == 1 {
+                let i = k * step + j;
+                anyhow::ensure!(i < width, "{s} doesn't fit in {width} bits");
+                bits[i] = true;
+            }
+        }
+    }
+    Ok(bits)
+}
+
+/// How to print a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// one `0` or `1` per wire, in wire order
+    Bits,
+    /// `0x` followed by the number in hexadecimal
+    Hex,
+    /// the number in decimal, for values of up to 128 bits (hexadecimal otherwise)
+    Int,
+}
+
+impl ValueFormat {
+    pub fn format(self, bits: &[bool]) -> String {
+        match self {
+            ValueFormat::Bits => bits.iter().map(|&b| if b { '1' } else { '0' }).collect(),
+            ValueFormat::Int if bits.len() <= 128 => bits
+                .iter()
+                .rev()
+                .fold(0u128, |n, &b| (n << 1) | u128::from(b))
+                .to_string(),
+            ValueFormat::Hex | ValueFormat::Int => {
+                let digits: String = bits
+                    .chunks(4)
+                    .rev()
+                    .map(|nibble| {
+                        let d = nibble
+                            .iter()
+                            .rev()
+                            .fold(0u32, |d, &b| (d << 1) | u32::from(b));
+                        char::from_digit(d, 16).expect("a nibble is a hex digit")
+                    })
+                    .collect();
+                format!("0x{digits}")
+            }
+        }
+    }
+}
+
+impl FromStr for ValueFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "bits" => Ok(ValueFormat::Bits),
+            "hex" => Ok(ValueFormat::Hex),
+            "int" => Ok(ValueFormat::Int),
+            _ => anyhow::bail!("unknown value format `{s}`, expected bits, hex or int"),
+        }
+    }
+}
+
+impl fmt::Display for ValueFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ValueFormat::Bits => "bits",
+            ValueFormat::Hex => "hex",
+            ValueFormat::Int => "int",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::test_util::{circuit, example_path, gate};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha12Rng;
+
+    fn bits_of(n: u64) -> Vec<bool> {
+        (0..64).map(|i| (n >> i) & 1 == 1).collect()
+    }
+
+    #[test]
+    fn example2_multiplies() {
+        let ckt = Circuit::from_bristol_fashion(&example_path("example2")).unwrap();
+        let mut rng = ChaCha12Rng::seed_from_u64(2);
+        for _ in 0..20 {
+            let (a, b): (u64, u64) = (rng.random(), rng.random());
+            // a single 128-bit input value, the two factors one after the other
+            let out = ckt.eval(&[[bits_of(a), bits_of(b)].concat()]).unwrap();
+            assert_eq!(out, vec![bits_of(a.wrapping_mul(b))]);
+        }
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let bits = parse_value("0x1f4", 12).unwrap();
+        assert_eq!(ValueFormat::Int.format(&bits), "500");
+        assert_eq!(ValueFormat::Hex.format(&bits), "0x1f4");
+        assert_eq!(ValueFormat::Bits.format(&bits), "001011111000");
+        assert_eq!(parse_value("500", 12).unwrap(), bits);
+        assert_eq!(parse_value("0b1_1111_0100", 12).unwrap(), bits);
+
+        assert!(parse_value("0x1000", 12).is_err());
+        assert!(parse_value("4096", 12).is_err());
+        assert!(parse_value("0xg", 12).is_err());
+
+        let wide = parse_value(&format!("0x1{}", "0".repeat(40)), 164).unwrap();
+        assert_eq!(wide.iter().position(|&b| b), Some(160));
+        assert_eq!(
+            ValueFormat::Int.format(&wide),
+            format!("0x1{}", "0".repeat(40))
+        );
+    }
+
+    #[test]
+    fn packed_lanes_match_single_evaluation() {
+        let ckt = Circuit::random_with(&crate::random::RandomCircuitConfig {
+            xor_ratio: 0.4,
+            inv_ratio: 0.1,
+            seed: 6,
+            ..crate::random::RandomCircuitConfig::new(24, 3000)
+        })
+        .unwrap()
+        .absorb_inverters();
+        assert!(
+            ckt.gates
+                .iter()
+                .any(|g| matches!(g.gate_type, GateType::Lut(_)))
+        );
+
+        let mut rng = ChaCha12Rng::seed_from_u64(7);
+        // a partial last pass too
+        let inputs: Vec<Vec<bool>> = (0..300)
+            .map(|_| (0..24).map(|_| rng.random()).collect())
+            .collect();
+        let batch = ckt.eval_bits_batch(&inputs).unwrap();
+        for (input, output) in inputs.iter().zip(&batch) {
+            assert_eq!(&ckt.eval_bits(input).unwrap(), output);
+        }
+
+        let words: Vec<u128> = (0..24).map(|_| rng.random()).collect();
+        let wide = ckt.eval_packed(&words).unwrap();
+        let low = ckt
+            .eval_packed(&words.iter().map(|&w| w as u64).collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(low, wide.iter().map(|&w| w as u64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn eval_checks_value_widths() {
+        let ckt = Circuit::from_bristol_fashion(&example_path("example2")).unwrap();
+        assert!(ckt.eval(&[vec![false; 64]]).is_err());
+        assert!(ckt.eval(&[vec![false; 64], vec![false; 64]]).is_err());
+    }
+
+    #[test]
+    fn eval_rejects_gates_out_of_order() {
+        // wire 3 is read before the AND drives it
+        let ckt = circuit(
+            2,
+            vec![
+                gate(GateType::Xor, &[3, 0], 4),
+                gate(GateType::And, &[0, 1], 3),
+            ],
+            vec![4],
+        );
+        assert!(ckt.eval_bits(&[true, true]).is_err());
+        assert!(ckt.eval_packed(&[u64::MAX, u64::MAX]).is_err());
+        let sorted = ckt.reorder().unwrap();
+        assert_eq!(sorted.eval_bits(&[true, true]).unwrap(), vec![false]);
+    }
+}
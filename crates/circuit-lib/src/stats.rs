@@ -0,0 +1,269 @@
+//! Structural statistics of a circuit, to size up a circuit before proving it.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::Circuit;
+
+/// Guest cycles per run, per gate and extra cycles per garbled table, fitted to the benchmarks
+/// in the README (within about 20% on all of them, from 4 to 45 million gates). The per-run
+/// cycles dominate below a few dozen gates.
+const CYCLES_PER_RUN: u64 = 60_000;
+const CYCLES_PER_GATE: u64 = 1214;
+const CYCLES_PER_TABLE: u64 = 127;
+
+/// Bytes the guest holds per gate of the circuit, per label, per wire slot and per table.
+const BYTES_PER_GATE: u64 = 16;
+const BYTES_PER_LABEL: u64 = 16;
+const BYTES_PER_WIRE: u64 = 33;
+const BYTES_PER_TABLE: u64 = 72;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitStats {
+    /// number of gates, a MAND line counting one per AND
+    pub num_gates: usize,
+    /// number of wires, inputs included
+    pub num_wires: usize,
+    /// number of input wires, over all input values
+    pub num_inputs: usize,
+    /// number of output wires, over all output values
+    pub num_outputs: usize,
+    /// number of gates of each type, by Bristol name
+    pub gate_counts: BTreeMap<String, usize>,
+    /// garbled tables the garbler builds, from the circuit in its garbling form
+    pub num_tables: usize,
+    /// AND-like gates on the longest path
+    pub multiplicative_depth: usize,
+    /// gates on the longest path
+    pub depth: usize,
+    /// most gates at the same depth
+    pub max_width: usize,
+    /// gates per depth level on average, `num_gates / depth`
+    pub avg_width: f64,
+    /// most wires holding a value that is still needed, evaluating the gates in order
+    pub peak_live_wires: usize,
+    /// number of input and gate wires by the number of gate inputs reading them
+    pub fan_out: BTreeMap<usize, usize>,
+    /// rough number of RISC Zero guest cycles to garble the circuit in its garbling form
+    pub estimated_cycles: u64,
+    /// rough peak guest memory in bytes for the circuit in its garbling form: the circuit, the
+    /// labels, the wire slots and the tables
+    pub estimated_memory_bytes: u64,
+}
+
+impl Circuit {
+    /// Compute [`CircuitStats`], sorting the gates first if they are out of order.
+    ///
+    /// The structure is that of the circuit as given, the garbling estimates are those of
+    /// [`Circuit::into_garbling_form`], which is what the garbler works on.
+    pub fn stats(&self) -> anyhow::Result<CircuitStats> {
+        let ckt = self.sorted()?;
+        let garbled = ckt.as_ref().clone().into_garbling_form()?;
+        Ok(ckt.stats_in_order(&garbled))
+    }
+
+    fn stats_in_order(&self, garbled: &Circuit) -> CircuitStats {
+        let mut gate_counts = BTreeMap::new();
+        let mut depth = vec![0usize; self.num_wires];
+        let mut and_depth = vec![0usize; self.num_wires];
+        let mut width: Vec<usize> = Vec::new();
+        let mut readers = vec![0usize; self.num_wires];
+        let mut last_use = vec![None; self.num_wires];
+
+        for (i, g) in self.gates.iter().enumerate() {
+            *gate_counts.entry(g.gate_type.to_string()).or_insert(0) += 1;
+            let d = 1 + g.inputs.iter().map(|&w| depth[w]).max().unwrap_or(0);
+            let and_d = g.inputs.iter().map(|&w| and_depth[w]).max().unwrap_or(0);
+            depth[g.output] = d;
+            and_depth[g.output] = and_d + usize::from(g.gate_type.is_and_like());
+            if width.len() < d {
+                width.resize(d, 0);
+            }
+            width[d - 1] += 1;
+            for &w in &g.inputs {
+                readers[w] += 1;
+                last_use[w] = Some(i);
+            }
+        }
+        for &w in &self.outputs {
+            last_use[w] = Some(self.gates.len());
+        }
+
+        // inputs are live until their last read, gate outputs from their gate to their last read
+        let mut live = self
+            .inputs
+            .iter()
+            .filter(|&&w| last_use[w].is_some())
+            .count();
+        let mut peak_live_wires = live;
+        for (i, g) in self.gates.iter().enumerate() {
+            live += 1;
+            peak_live_wires = peak_live_wires.max(live);
+            let mut dying: Vec<_> = g
+                .inputs
+                .iter()
+                .filter(|&&w| last_use[w] == Some(i))
+                .collect();
+            dying.dedup();
+            live -= dying.len();
+            if last_use[g.output].is_none() {
+                live -= 1;
+            }
+        }
+
+        let mut fan_out = BTreeMap::new();
+        let wires = self
+            .inputs
+            .iter()
+            .chain(self.gates.iter().map(|g| &g.output));
+        for &w in wires {
+            *fan_out.entry(readers[w]).or_insert(0) += 1;
+        }
+
+        let num_tables = garbled
+            .gates
+            .iter()
+            .filter(|g| g.gate_type.needs_table())
+            .count();
+        let garbled_gates = garbled.gates.len() as u64;
+        let estimated_cycles =
+            CYCLES_PER_RUN + CYCLES_PER_GATE * garbled_gates + CYCLES_PER_TABLE * num_tables as u64;
+        let estimated_memory_bytes = BYTES_PER_GATE * garbled_gates
+            + BYTES_PER_LABEL * (garbled.inputs.len() + num_tables) as u64
+            + BYTES_PER_WIRE * garbled.num_wires as u64
+            + BYTES_PER_TABLE * num_tables as u64;
+        let num_gates = self.gates.len();
+
+        CircuitStats {
+            num_gates,
+            num_wires: self.num_wires,
+            num_inputs: self.inputs.len(),
+            num_outputs: self.outputs.len(),
+            gate_counts,
+            num_tables,
+            multiplicative_depth: and_depth.into_iter().max().unwrap_or(0),
+            depth: width.len(),
+            max_width: width.iter().copied().max().unwrap_or(0),
+            avg_width: if width.is_empty() {
+                0.0
+            } else {
+                num_gates as f64 / width.len() as f64
+            },
+            peak_live_wires,
+            fan_out,
+            estimated_cycles,
+            estimated_memory_bytes,
+        }
+    }
+}
+
+impl Display for CircuitStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "gates: {}, wires: {}, inputs: {}, outputs: {}",
+            self.num_gates, self.num_wires, self.num_inputs, self.num_outputs
+        )?;
+        let counts: Vec<String> = self
+            .gate_counts
+            .iter()
+            .map(|(name, n)| format!("{name} {n}"))
+            .collect();
+        writeln!(f, "gate types: {}", counts.join(", "))?;
+        writeln!(f, "garbled tables: {}", self.num_tables)?;
+        writeln!(
+            f,
+            "depth: {} (multiplicative {})",
+            self.depth, self.multiplicative_depth
+        )?;
+        writeln!(
+            f,
+            "layer width: max {}, average {:.1}",
+            self.max_width, self.avg_width
+        )?;
+        writeln!(f, "peak live wires: {}", self.peak_live_wires)?;
+        let fan_out: Vec<String> = self
+            .fan_out
+            .iter()
+            .map(|(k, n)| format!("{k}: {n}"))
+            .collect();
+        writeln!(f, "fan-out (readers: wires): {}", fan_out.join(", "))?;
+        writeln!(f, "estimated guest cycles: {}", self.estimated_cycles)?;
+        write!(
+            f,
+            "estimated guest memory: {:.2} MB",
+            self.estimated_memory_bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GateType;
+    use crate::opt::test_util::{circuit, example_path, gate};
+
+    #[test]
+    fn stats_of_example1() {
+        let stats = Circuit::from_bristol_fashion(&example_path("example1"))
+            .unwrap()
+            .stats()
+            .unwrap();
+        assert_eq!(stats.num_gates, 4);
+        assert_eq!(stats.gate_counts["AND"], 2);
+        assert_eq!(stats.gate_counts["XOR"], 2);
+        assert_eq!(stats.num_tables, 2);
+        assert_eq!(stats.depth, 3);
+        assert_eq!(stats.multiplicative_depth, 2);
+        assert_eq!(stats.max_width, 2);
+        // all five inputs and the first AND
+        assert_eq!(stats.peak_live_wires, 6);
+        assert_eq!(stats.fan_out, BTreeMap::from([(0, 1), (1, 8)]));
+    }
+
+    #[test]
+    fn layers_of_random_circuits_match_their_shape() {
+        let ckt = Circuit::random_with(&crate::random::RandomCircuitConfig {
+            shape: crate::random::Shape::Width(10),
+            ..crate::random::RandomCircuitConfig::new(10, 100)
+        })
+        .unwrap();
+        let stats = ckt.stats().unwrap();
+        assert_eq!(stats.num_gates, 100);
+        assert!(stats.max_width >= 10);
+        assert!(stats.depth <= 10);
+        assert_eq!(stats.fan_out.values().sum::<usize>(), 110);
+    }
+
+    #[test]
+    fn sorts_gates_out_of_order_first() {
+        let ckt = Circuit::read_bristol_fashion(
+            "3 4\n1 1\n1 1\n\n2 1 1 2 3 AND\n1 1 1 1 EQ\n1 1 0 2 EQ\n".as_bytes(),
+        )
+        .unwrap();
+        assert!(!ckt.is_in_evaluation_order());
+        let stats = ckt.stats().unwrap();
+        assert_eq!(stats.num_gates, 3);
+        assert_eq!(stats.depth, 2);
+        assert_eq!(stats.multiplicative_depth, 1);
+        // the constants fold the AND away
+        assert_eq!(stats.num_tables, 0);
+    }
+
+    #[test]
+    fn counts_the_tables_of_the_garbling_form() {
+        let ckt = circuit(
+            2,
+            vec![
+                gate(GateType::Lut(0x5), &[0, 1], 2),
+                gate(GateType::Lut(0xC), &[2, 1], 3),
+                gate(GateType::Or, &[3, 0], 4),
+            ],
+            vec![4],
+        );
+        // NOT b OR a is a single AND-like table once the inverter is absorbed
+        assert_eq!(ckt.stats().unwrap().num_tables, 1);
+    }
+}
@@ -0,0 +1,257 @@
+//! Canonical encoding of a [`Circuit`] and the circuit hash derived from it.
+//!
+//! The guest commits to `SHA-256(canonical_encoding(circuit))` instead of hashing the rkyv
+//! archive, so the commitment does not depend on rkyv's layout or on derived fields such as
+//! `total_gate_count` and the per-type gate counts.
+//!
+//! The encoded circuit is the one the garbler garbles: a binary file as is, a text file in its
+//! garbling form, after reordering, constant folding, inverter absorption and renumbering (see
+//! `circuit_lib::Circuit::into_garbling_form`). Recomputing the hash of a `.bristol` file
+//! therefore takes this exact optimizer, through [`circuit_digest`] or
+//! `circuit-utils convert --to binary`. The encoding is also `circuit_lib`'s binary circuit
+//! format, so the hash of a binary file, such as the output of that command, is plain SHA-256
+//! of the file and can be checked in any language against the layout documented in
+//! [`circuit_lib::binary`], whose encoder this module drives.
+
+use std::{io, path::Path};
+
+use sha2::{Digest, Sha256};
+
+use crate::input::{Circuit, GateDef};
+use crate::parse::parse_circuit;
+
+use circuit_lib::binary::CanonicalEncoder;
+pub use circuit_lib::binary::{
+    CIRCUIT_DOMAIN, OP_AND, OP_BUF, OP_CONST, OP_INV, OP_LUT, OP_XNOR, OP_XOR,
+};
+
+impl Circuit {
+    /// Feed the canonical encoding of the circuit to `emit`, piece by piece.
+    ///
+    /// Streaming keeps the guest from materializing a second copy of a large circuit.
+    pub fn encode_canonical(&self, mut emit: impl FnMut(&[u8])) {
+        self.try_encode_canonical(|bytes| {
+            emit(bytes);
+            Ok(())
+        })
+        .expect("wire id or count exceeds u32::MAX");
+    }
+
+    fn try_encode_canonical(&self, emit: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        let mut encoder = CanonicalEncoder::new(
+            emit,
+            self.total_wire_count,
+            self.input1_count,
+            self.input2_count,
+            self.output_wire_count,
+            self.gates.len(),
+        )?;
+        for gate in &self.gates {
+            match *gate {
+                GateDef::And { in0, in1, out } => encoder.and(in0, in1, out)?,
+                GateDef::Xor { in0, in1, out } => encoder.xor(in0, in1, out)?,
+                GateDef::Not { input, out } => encoder.inv(input, out)?,
+                GateDef::Const { value, out } => encoder.constant(value, out)?,
+                GateDef::Buf { input, out } => encoder.buf(input, out)?,
+                GateDef::Xnor { in0, in1, out } => encoder.xnor(in0, in1, out)?,
+                GateDef::Lut {
+                    truth_table,
+                    in0,
+                    in1,
+                    out,
+                } => encoder.lut(truth_table, in0, in1, out)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The canonical encoding as a byte vector.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_canonical(|chunk| bytes.extend_from_slice(chunk));
+        bytes
+    }
+
+    /// SHA-256 of the canonical encoding. This is the `circuit_hash` committed by the guest.
+    pub fn canonical_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        self.encode_canonical(|chunk| hasher.update(chunk));
+        hasher.finalize().into()
+    }
+}
+
+/// Compute the circuit hash of the Bristol or binary file at `path`, as committed by the guest.
+pub fn circuit_digest<P: AsRef<Path>>(path: P) -> anyhow::Result<[u8; 32]> {
+    Ok(parse_circuit(path)?.canonical_hash())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_bristol;
+    use crate::test_util::example_path;
+
+    fn single_and() -> Circuit {
+        Circuit {
+            total_gate_count: 1,
+            and_gate_count: 1,
+            not_gate_count: 0,
+            xor_gate_count: 0,
+            const_gate_count: 0,
+            lut_gate_count: 0,
+            total_wire_count: 3,
+            input1_count: 1,
+            input2_count: 1,
+            output_wire_count: 1,
+            gates: vec![GateDef::And {
+                in0: 0,
+                in1: 1,
+                out: 2,
+            }],
+        }
+    }
+
+    #[test]
+    fn canonical_bytes_follow_documented_layout() {
+        let mut expected = CIRCUIT_DOMAIN.to_vec();
+        for v in [3u32, 1, 1, 1, 1] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        expected.push(OP_AND);
+        for v in [0u32, 1, 2] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(single_and().canonical_bytes(), expected);
+        assert_eq!(
+            single_and().canonical_hash(),
+            <[u8; 32]>::from(Sha256::digest(&expected))
+        );
+    }
+
+    #[test]
+    fn hash_ignores_derived_counts() {
+        let mut ckt = single_and();
+        let reference = ckt.canonical_hash();
+        ckt.total_gate_count = 42;
+        ckt.and_gate_count = 0;
+        ckt.xor_gate_count = 7;
+        assert_eq!(ckt.canonical_hash(), reference);
+    }
+
+    #[test]
+    fn hash_depends_on_gates_and_io_layout() {
+        let reference = single_and().canonical_hash();
+
+        let mut ckt = single_and();
+        ckt.gates[0] = GateDef::Xor {
+            in0: 0,
+            in1: 1,
+            out: 2,
+        };
+        assert_ne!(ckt.canonical_hash(), reference);
+
+        let mut ckt = single_and();
+        ckt.input1_count = 2;
+        ckt.input2_count = 0;
+        assert_ne!(ckt.canonical_hash(), reference);
+    }
+
+    #[test]
+    fn digest_of_example1_matches_parsed_circuit() {
+        let ckt = parse_bristol(example_path("example1")).unwrap();
+        assert_eq!(
+            circuit_digest(example_path("example1")).unwrap(),
+            ckt.canonical_hash()
+        );
+        // 4 binary gates of 13 bytes each, no INV
+        assert_eq!(
+            ckt.canonical_bytes().len(),
+            CIRCUIT_DOMAIN.len() + 5 * 4 + 4 * 13
+        );
+    }
+
+    #[test]
+    fn text_circuits_are_committed_in_garbling_form() {
+        // INV 0 -> 3, AND 3 1 -> 4, with wire 2 unused
+        let path = std::env::temp_dir().join("garble_digest_garbling_form.bristol");
+        std::fs::write(&path, "2 5\n2 1 1\n1 1\n\n1 1 0 3 INV\n2 1 3 1 4 AND\n").unwrap();
+        let digest = circuit_digest(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // a single NOT a AND b gate on dense wires
+        let mut expected = CIRCUIT_DOMAIN.to_vec();
+        for v in [3u32, 1, 1, 1, 1] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        expected.extend_from_slice(&[OP_LUT, 0x2]);
+        for v in [0u32, 1, 2] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(digest.unwrap(), <[u8; 32]>::from(Sha256::digest(&expected)));
+    }
+
+    #[test]
+    fn encodes_every_opcode_like_write_binary() {
+        use circuit_lib::GateType;
+
+        let gates = [
+            (GateType::And, vec![0, 1]),
+            (GateType::Xor, vec![0, 2]),
+            (GateType::Inv, vec![3]),
+            (GateType::Const(true), vec![]),
+            (GateType::Buf, vec![5]),
+            (GateType::Xnor, vec![6, 1]),
+            (GateType::Lut(0xE), vec![7, 0]),
+        ];
+        let ir = circuit_lib::Circuit {
+            num_wires: 3 + gates.len(),
+            inputs: vec![0, 1, 2],
+            outputs: vec![3 + gates.len() - 1],
+            input_groups: vec![1, 2],
+            output_groups: vec![1],
+            gates: gates
+                .into_iter()
+                .enumerate()
+                .map(|(i, (gate_type, inputs))| circuit_lib::Gate {
+                    gate_type,
+                    inputs,
+                    output: 3 + i,
+                })
+                .collect(),
+        };
+        let ckt = Circuit::try_from(&ir).unwrap();
+        assert!(matches!(ckt.gates[2], GateDef::Not { .. }));
+        assert!(matches!(ckt.gates[6], GateDef::Lut { .. }));
+
+        let mut bytes = Vec::new();
+        ir.write_binary(&mut bytes).unwrap();
+        assert_eq!(ckt.canonical_bytes(), bytes);
+        let opcodes = [OP_AND, OP_XOR, OP_INV, OP_CONST, OP_BUF, OP_XNOR, OP_LUT];
+        let mut pos = CIRCUIT_DOMAIN.len() + 5 * 4;
+        for (op, len) in opcodes.into_iter().zip([13, 13, 9, 6, 9, 13, 14]) {
+            assert_eq!(bytes[pos], op);
+            pos += len;
+        }
+        assert_eq!(pos, bytes.len());
+    }
+
+    #[test]
+    fn binary_file_is_the_canonical_encoding() {
+        let ir = circuit_lib::Circuit::random(8, 300, 0.5, 3)
+            .absorb_inverters()
+            .with_standard_layout()
+            .unwrap();
+        let mut bytes = Vec::new();
+        ir.write_binary(&mut bytes).unwrap();
+        let ckt = Circuit::try_from(&ir).unwrap();
+        assert_eq!(bytes, ckt.canonical_bytes());
+
+        let path = std::env::temp_dir().join("garble_digest_binary.bin");
+        std::fs::write(&path, &bytes).unwrap();
+        let loaded = parse_circuit(&path);
+        let digest = circuit_digest(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().gates, ckt.gates);
+        assert_eq!(digest.unwrap(), <[u8; 32]>::from(Sha256::digest(&bytes)));
+    }
+}
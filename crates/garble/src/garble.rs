@@ -1,5 +1,5 @@
 use crate::input::{Circuit, GateDef, Label, LabelInputs, WireLabels};
-use crate::output::{AndGateTable, GarbledTables, NotGateTable};
+use crate::output::{AndGateTable, ConstLabel, GarbledTables, LutGateTable, NotGateTable};
 use sha2::{Digest, Sha256};
 
 // this xors the 128 bit labels using u32 operations optimized for RISC0's 32-bit emulation
@@ -61,6 +61,43 @@ fn pad_sha(ka: &Label, kb: &Label) -> Label {
     out
 }
 
+/// Four-row garbled table of a 2-input gate; bit `2a + b` of `truth_table` is the output for
+/// inputs `(a, b)`.
+fn garble_binary(
+    lu: &WireLabels,
+    lv: &WireLabels,
+    k0_out: &Label,
+    k1_out: &Label,
+    truth_table: u8,
+) -> [[u8; 16]; 4] {
+    let mut table: [[u8; 16]; 4] = [[0u8; 16]; 4];
+    let combos = [(0u8, 0u8), (0, 1), (1, 0), (1, 1)];
+    for (i, (a, b)) in combos.iter().enumerate() {
+        let ka = if *a == 0 { lu.k0 } else { lu.k1 };
+        let kb = if *b == 0 { lv.k0 } else { lv.k1 };
+        let out_bit = (truth_table >> (2 * a + b)) & 1;
+        let kout = if out_bit == 0 { k0_out } else { k1_out };
+        let p = pad_sha(&ka, &kb);
+        let ct = xor_labels(&p, kout);
+        table[i] = ct;
+    }
+    table
+}
+
+/// Two-row garbled table of an INV gate.
+fn garble_not(lu: &WireLabels, k0_out: &Label, k1_out: &Label) -> [[u8; 16]; 2] {
+    let mut table: [[u8; 16]; 2] = [[0u8; 16]; 2];
+    for (i, &a) in [0u8, 1].iter().enumerate() {
+        let ka = if a == 0 { lu.k0 } else { lu.k1 };
+        let out_bit = 1 - a;
+        let kout = if out_bit == 0 { k0_out } else { k1_out };
+        let p = pad_sha(&ka, &ka); // unary, duplicate
+        let ct = xor_labels(&p, kout);
+        table[i] = ct;
+    }
+    table
+}
+
 /// Returns garbled tables corresponding to a circuit, delta and label_list for input and inner gate wires
 /// (except for XOR).
 pub fn garble_ckt(ckt_inputs: Circuit, label_inputs: LabelInputs) -> GarbledTables {
@@ -91,6 +128,8 @@ pub fn garble_ckt(ckt_inputs: Circuit, label_inputs: LabelInputs) -> GarbledTabl
     // 3) Prepare output tables
     let mut and_tables = Vec::new();
     let mut not_tables = Vec::new();
+    let mut const_labels = Vec::new();
+    let mut lut_tables = Vec::new();
 
     //generate the garbled table
     for (idx, gate) in gates.iter().enumerate() {
@@ -115,17 +154,7 @@ pub fn garble_ckt(ckt_inputs: Circuit, label_inputs: LabelInputs) -> GarbledTabl
                     k1: k1_out,
                 });
 
-                let mut table: [[u8; 16]; 4] = [[0u8; 16]; 4];
-                let combos = [(0u8, 0u8), (0, 1), (1, 0), (1, 1)];
-                for (i, (a, b)) in combos.iter().enumerate() {
-                    let ka = if *a == 0 { lu.k0 } else { lu.k1 };
-                    let kb = if *b == 0 { lv.k0 } else { lv.k1 };
-                    let out_bit = a & b;
-                    let kout = if out_bit == 0 { k0_out } else { k1_out };
-                    let p = pad_sha(&ka, &kb);
-                    let ct = xor_labels(&p, &kout);
-                    table[i] = ct;
-                }
+                let table = garble_binary(&lu, &lv, &k0_out, &k1_out, 0x8);
 
                 and_tables.push(AndGateTable {
                     gate: idx,
@@ -146,15 +175,7 @@ pub fn garble_ckt(ckt_inputs: Circuit, label_inputs: LabelInputs) -> GarbledTabl
                     k1: k1_out,
                 });
 
-                let mut table: [[u8; 16]; 2] = [[0u8; 16]; 2];
-                for (i, &a) in [0u8, 1].iter().enumerate() {
-                    let ka = if a == 0 { lu.k0 } else { lu.k1 };
-                    let out_bit = 1 - a;
-                    let kout = if out_bit == 0 { k0_out } else { k1_out };
-                    let p = pad_sha(&ka, &ka); // unary, duplicate
-                    let ct = xor_labels(&p, &kout);
-                    table[i] = ct;
-                }
+                let table = garble_not(&lu, &k0_out, &k1_out);
 
                 not_tables.push(NotGateTable {
                     gate: idx,
@@ -163,15 +184,229 @@ pub fn garble_ckt(ckt_inputs: Circuit, label_inputs: LabelInputs) -> GarbledTabl
                     table,
                 });
             }
+
+            GateDef::Const { value, out } => {
+                // free: publish the label matching the constant, no table needed
+                let k0 = inner_iter.next().unwrap();
+                let k1 = xor_labels(&k0, &delta);
+                const_labels.push(ConstLabel {
+                    gate: idx,
+                    out,
+                    label: if value { k1 } else { k0 },
+                });
+                wires[out] = Some(WireLabels { k0, k1 });
+            }
+
+            GateDef::Buf { input, out } => {
+                // free: the copy shares the labels of its input
+                wires[out] = wires[input].clone();
+            }
+
+            GateDef::Xnor { in0, in1, out } => {
+                // free: XOR with the roles of the two labels swapped
+                let lu = wires[in0].as_ref().unwrap();
+                let lv = wires[in1].as_ref().unwrap();
+                let k1 = xor_labels(&lu.k0, &lv.k0);
+                let k0 = xor_labels(&k1, &delta);
+                wires[out] = Some(WireLabels { k0, k1 });
+            }
+
+            GateDef::Lut {
+                truth_table,
+                in0,
+                in1,
+                out,
+            } => {
+                let lu = wires[in0].clone().unwrap();
+                let lv = wires[in1].clone().unwrap();
+
+                let k0_out = inner_iter.next().unwrap();
+                let k1_out = xor_labels(&k0_out, &delta);
+                wires[out] = Some(WireLabels {
+                    k0: k0_out,
+                    k1: k1_out,
+                });
+
+                let table = garble_binary(&lu, &lv, &k0_out, &k1_out, truth_table);
+
+                lut_tables.push(LutGateTable {
+                    gate: idx,
+                    truth_table,
+                    in0,
+                    in1,
+                    out,
+                    table,
+                });
+            }
         }
     }
 
     GarbledTables {
         and_tables,
         not_tables,
+        const_labels,
+        lut_tables,
     }
 }
 
+/// [`garble_ckt`] on all cores, for the host: the gates of each level of the circuit (see
+/// [`circuit_lib::levels`]) are garbled concurrently. The tables are the same, byte for byte.
+#[cfg(feature = "parallel")]
+pub fn garble_ckt_parallel(ckt_inputs: Circuit, label_inputs: LabelInputs) -> GarbledTables {
+    use circuit_lib::levels::Levels;
+    use rayon::prelude::*;
+
+    /// What garbling a gate publishes besides its output labels
+    enum Garbled {
+        Free,
+        And([[u8; 16]; 4]),
+        Not([[u8; 16]; 2]),
+        Const(Label),
+        Lut([[u8; 16]; 4]),
+    }
+
+    let gates = ckt_inputs.gates;
+    let delta = label_inputs.delta;
+    let inner = label_inputs.inner_labels;
+
+    // AND, NOT, constant and LUT gates take the next inner label, in circuit order
+    let mut fresh = Vec::with_capacity(gates.len());
+    let mut next_inner = 0;
+    for gate in &gates {
+        fresh.push(next_inner);
+        if matches!(
+            gate,
+            GateDef::And { .. } | GateDef::Not { .. } | GateDef::Const { .. } | GateDef::Lut { .. }
+        ) {
+            next_inner += 1;
+        }
+    }
+
+    let mut wires: Vec<Option<WireLabels>> = vec![None; ckt_inputs.total_wire_count];
+    for (w, k0) in label_inputs.input_labels.into_iter().enumerate() {
+        let k1 = xor_labels(&k0, &delta);
+        wires[w] = Some(WireLabels { k0, k1 });
+    }
+    let fresh_labels = |idx: usize| {
+        let k0 = inner[fresh[idx]];
+        let k1 = xor_labels(&k0, &delta);
+        WireLabels { k0, k1 }
+    };
+
+    let levels = Levels::new(
+        ckt_inputs.total_wire_count,
+        gates.iter().map(|g| (g.inputs(), g.output())),
+    );
+    let mut garbled: Vec<Option<Garbled>> = (0..gates.len()).map(|_| None).collect();
+    for level in levels.iter() {
+        let results: Vec<(usize, WireLabels, Garbled)> = level
+            .par_iter()
+            .with_min_len(64)
+            .map(|&idx| {
+                let wire = |w: usize| wires[w].as_ref().unwrap();
+                match gates[idx] {
+                    GateDef::Xor { in0, in1, .. } => {
+                        let k0 = xor_labels(&wire(in0).k0, &wire(in1).k0);
+                        let k1 = xor_labels(&k0, &delta);
+                        (idx, WireLabels { k0, k1 }, Garbled::Free)
+                    }
+                    GateDef::Xnor { in0, in1, .. } => {
+                        let k1 = xor_labels(&wire(in0).k0, &wire(in1).k0);
+                        let k0 = xor_labels(&k1, &delta);
+                        (idx, WireLabels { k0, k1 }, Garbled::Free)
+                    }
+                    GateDef::Buf { input, .. } => (idx, wire(input).clone(), Garbled::Free),
+                    GateDef::And { in0, in1, .. } => {
+                        let out = fresh_labels(idx);
+                        let table = garble_binary(wire(in0), wire(in1), &out.k0, &out.k1, 0x8);
+                        (idx, out, Garbled::And(table))
+                    }
+                    GateDef::Lut {
+                        truth_table,
+                        in0,
+                        in1,
+                        ..
+                    } => {
+                        let out = fresh_labels(idx);
+                        let table =
+                            garble_binary(wire(in0), wire(in1), &out.k0, &out.k1, truth_table);
+                        (idx, out, Garbled::Lut(table))
+                    }
+                    GateDef::Not { input, .. } => {
+                        let out = fresh_labels(idx);
+                        let table = garble_not(wire(input), &out.k0, &out.k1);
+                        (idx, out, Garbled::Not(table))
+                    }
+                    GateDef::Const { value, .. } => {
+                        let out = fresh_labels(idx);
+                        let label = if value { out.k1 } else { out.k0 };
+                        (idx, out, Garbled::Const(label))
+                    }
+                }
+            })
+            .collect();
+        for (idx, labels, result) in results {
+            wires[gates[idx].output()] = Some(labels);
+            garbled[idx] = Some(result);
+        }
+    }
+
+    // the tables in circuit order, as the sequential garbler emits them
+    let mut tables = GarbledTables {
+        and_tables: Vec::with_capacity(ckt_inputs.and_gate_count),
+        not_tables: Vec::with_capacity(ckt_inputs.not_gate_count),
+        const_labels: Vec::with_capacity(ckt_inputs.const_gate_count),
+        lut_tables: Vec::with_capacity(ckt_inputs.lut_gate_count),
+    };
+    for (idx, (gate, result)) in gates.iter().zip(garbled).enumerate() {
+        match (gate, result.expect("every gate is on a level")) {
+            (&GateDef::And { in0, in1, out }, Garbled::And(table)) => {
+                tables.and_tables.push(AndGateTable {
+                    gate: idx,
+                    in0,
+                    in1,
+                    out,
+                    table,
+                })
+            }
+            (&GateDef::Not { input, out }, Garbled::Not(table)) => {
+                tables.not_tables.push(NotGateTable {
+                    gate: idx,
+                    input,
+                    out,
+                    table,
+                })
+            }
+            (&GateDef::Const { out, .. }, Garbled::Const(label)) => {
+                tables.const_labels.push(ConstLabel {
+                    gate: idx,
+                    out,
+                    label,
+                })
+            }
+            (
+                &GateDef::Lut {
+                    truth_table,
+                    in0,
+                    in1,
+                    out,
+                },
+                Garbled::Lut(table),
+            ) => tables.lut_tables.push(LutGateTable {
+                gate: idx,
+                truth_table,
+                in0,
+                in1,
+                out,
+                table,
+            }),
+            (_, Garbled::Free) => {}
+            _ => unreachable!("each gate is garbled according to its type"),
+        }
+    }
+    tables
+}
+
 #[cfg(test)]
 mod tests {
     use crate::garble::garble_ckt;
@@ -248,6 +483,8 @@ mod tests {
             and_gate_count: 1,
             not_gate_count: 0,
             xor_gate_count: 0,
+            const_gate_count: 0,
+            lut_gate_count: 0,
             output_wire_count: 1,
         };
 
@@ -280,4 +517,322 @@ mod tests {
         assert_eq!(&xor_labels(&pad_sha(&l_a1, &l_b0), &t.table[2]), &l_c0);
         assert_eq!(&xor_labels(&pad_sha(&l_a1, &l_b1), &t.table[3]), &l_c1);
     }
+
+    #[test]
+    fn test_garble_const_and_buf_are_free() {
+        // wire 1 = const 1, wire 2 = copy of input 0, wire 3 = 2 AND 1
+        let ckt = Circuit {
+            total_wire_count: 4,
+            total_gate_count: 3,
+            input1_count: 1,
+            input2_count: 0,
+            gates: vec![
+                GateDef::Const {
+                    value: true,
+                    out: 1,
+                },
+                GateDef::Buf { input: 0, out: 2 },
+                GateDef::And {
+                    in0: 2,
+                    in1: 1,
+                    out: 3,
+                },
+            ],
+            and_gate_count: 1,
+            not_gate_count: 0,
+            xor_gate_count: 0,
+            const_gate_count: 1,
+            lut_gate_count: 0,
+            output_wire_count: 1,
+        };
+        assert_eq!(ckt.get_inner_wire_count(), 2);
+
+        let labels = LabelInputs {
+            delta: [8u8; 16],
+            inner_labels: vec![[3u8; 16], [7u8; 16]],
+            input_labels: vec![[2u8; 16]],
+        };
+        let l_a0 = labels.input_labels[0];
+        let l_c1 = xor_labels(&labels.inner_labels[0], &labels.delta);
+        let l_out0 = labels.inner_labels[1];
+
+        let tbls = garble_ckt(ckt, labels.clone());
+        assert!(tbls.not_tables.is_empty());
+        assert_eq!(tbls.const_labels.len(), 1);
+        assert_eq!(tbls.const_labels[0].out, 1);
+        assert_eq!(tbls.const_labels[0].label, l_c1);
+
+        // the AND reads the copy of wire 0 and the published constant label
+        let t = &tbls.and_tables[0];
+        assert_eq!(&xor_labels(&pad_sha(&l_a0, &l_c1), &t.table[1]), &l_out0);
+    }
+
+    #[test]
+    fn test_garble_xnor_is_free_and_or_uses_one_table() {
+        // wire 2 = 0 XNOR 1, wire 3 = 0 OR 1
+        let ir = circuit_lib::Circuit {
+            num_wires: 4,
+            inputs: vec![0, 1],
+            outputs: vec![2, 3],
+            input_groups: vec![1, 1],
+            output_groups: vec![2],
+            gates: vec![
+                circuit_lib::Gate {
+                    gate_type: circuit_lib::GateType::Xnor,
+                    inputs: vec![0, 1],
+                    output: 2,
+                },
+                circuit_lib::Gate {
+                    gate_type: circuit_lib::GateType::Or,
+                    inputs: vec![0, 1],
+                    output: 3,
+                },
+            ],
+        };
+        let ckt = Circuit::try_from(&ir).unwrap();
+        assert_eq!(ckt.get_inner_wire_count(), 1);
+
+        let labels = LabelInputs {
+            delta: [8u8; 16],
+            inner_labels: vec![[7u8; 16]],
+            input_labels: vec![[2u8; 16], [5u8; 16]],
+        };
+        let l_a0 = labels.input_labels[0];
+        let l_a1 = xor_labels(&l_a0, &labels.delta);
+        let l_b0 = labels.input_labels[1];
+        let l_b1 = xor_labels(&l_b0, &labels.delta);
+        let l_c0 = labels.inner_labels[0];
+        let l_c1 = xor_labels(&l_c0, &labels.delta);
+
+        let tbls = garble_ckt(ckt, labels.clone());
+        assert!(tbls.and_tables.is_empty());
+        assert_eq!(tbls.lut_tables.len(), 1);
+        let t = &tbls.lut_tables[0];
+        assert_eq!(t.truth_table, 0xE);
+        assert_eq!(&xor_labels(&pad_sha(&l_a0, &l_b0), &t.table[0]), &l_c0);
+        assert_eq!(&xor_labels(&pad_sha(&l_a0, &l_b1), &t.table[1]), &l_c1);
+        assert_eq!(&xor_labels(&pad_sha(&l_a1, &l_b0), &t.table[2]), &l_c1);
+        assert_eq!(&xor_labels(&pad_sha(&l_a1, &l_b1), &t.table[3]), &l_c1);
+    }
+
+    /// Evaluate the garbled tables on the labels of `inputs` and decode the outputs. There is no
+    /// point-and-permute, so the row of a table is found by decoding its input labels against
+    /// the garbler's zero labels: not something a real evaluator can do, but every decrypted
+    /// label is checked to be one of the two labels of its wire.
+    fn eval_garbled(
+        ckt: &Circuit,
+        labels: &LabelInputs,
+        tables: &crate::output::GarbledTables,
+        inputs: &[bool],
+    ) -> Vec<bool> {
+        let delta = labels.delta;
+        let decode = |held: &Label, k0: &Label| {
+            if held == k0 {
+                false
+            } else {
+                assert_eq!(held, &xor_labels(k0, &delta), "label of neither bit");
+                true
+            }
+        };
+        let mut and_rows = tables.and_tables.iter().map(|t| (t.gate, t.table));
+        let mut lut_rows = tables.lut_tables.iter().map(|t| (t.gate, t.table));
+        let mut not_rows = tables.not_tables.iter().map(|t| (t.gate, t.table));
+        let mut const_labels = tables.const_labels.iter().map(|c| (c.gate, c.label));
+        let mut inner = labels.inner_labels.iter().copied();
+
+        // (zero label, label held by the evaluator) of each wire
+        let mut wires = vec![([0u8; 16], [0u8; 16]); ckt.total_wire_count];
+        for (i, (&k0, &bit)) in labels.input_labels.iter().zip(inputs).enumerate() {
+            wires[i] = (k0, if bit { xor_labels(&k0, &delta) } else { k0 });
+        }
+        for (idx, gate) in ckt.gates.iter().enumerate() {
+            let (out, wire) = match *gate {
+                GateDef::Xor { in0, in1, out } | GateDef::Xnor { in0, in1, out } => {
+                    let ((ka, ha), (kb, hb)) = (wires[in0], wires[in1]);
+                    let mut k0 = xor_labels(&ka, &kb);
+                    if matches!(gate, GateDef::Xnor { .. }) {
+                        k0 = xor_labels(&k0, &delta);
+                    }
+                    (out, (k0, xor_labels(&ha, &hb)))
+                }
+                GateDef::And { in0, in1, out } | GateDef::Lut { in0, in1, out, .. } => {
+                    let ((ka, ha), (kb, hb)) = (wires[in0], wires[in1]);
+                    let (gate_idx, table) = if matches!(gate, GateDef::And { .. }) {
+                        and_rows.next().unwrap()
+                    } else {
+                        lut_rows.next().unwrap()
+                    };
+                    assert_eq!(gate_idx, idx);
+                    let row = 2 * usize::from(decode(&ha, &ka)) + usize::from(decode(&hb, &kb));
+                    let held = xor_labels(&table[row], &pad_sha(&ha, &hb));
+                    (out, (inner.next().unwrap(), held))
+                }
+                GateDef::Not { input, out } => {
+                    let (ka, ha) = wires[input];
+                    let (gate_idx, table) = not_rows.next().unwrap();
+                    assert_eq!(gate_idx, idx);
+                    let held =
+                        xor_labels(&table[usize::from(decode(&ha, &ka))], &pad_sha(&ha, &ha));
+                    (out, (inner.next().unwrap(), held))
+                }
+                GateDef::Const { out, .. } => {
+                    let (gate_idx, label) = const_labels.next().unwrap();
+                    assert_eq!(gate_idx, idx);
+                    (out, (inner.next().unwrap(), label))
+                }
+                GateDef::Buf { input, out } => (out, wires[input]),
+            };
+            decode(&wire.1, &wire.0);
+            wires[out] = wire;
+        }
+
+        let first_output = ckt.total_wire_count - ckt.output_wire_count;
+        wires[first_output..]
+            .iter()
+            .map(|(k0, held)| decode(held, k0))
+            .collect()
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_garbling_is_byte_identical() {
+        use crate::garble::garble_ckt_parallel;
+        use circuit_lib::random::RandomCircuitConfig;
+        use rkyv::rancor::Error;
+
+        let ir = circuit_lib::Circuit::random_with(&RandomCircuitConfig {
+            xor_ratio: 0.5,
+            inv_ratio: 0.1,
+            seed: 11,
+            ..RandomCircuitConfig::new(32, 5000)
+        })
+        .unwrap()
+        .with_standard_layout()
+        .unwrap();
+        // INV gates, and the LUT and constant gates inverter absorption leaves instead
+        let mut absorbed = ir.absorb_inverters();
+        absorbed.add_const_wire(true);
+        let absorbed = absorbed.with_standard_layout().unwrap();
+
+        for ir in [ir, absorbed] {
+            let ckt = Circuit::try_from(&ir).unwrap();
+            let labels = crate::input::gen_labels(
+                [4u8; 32],
+                ckt.get_input_wire_count(),
+                ckt.get_inner_wire_count(),
+            );
+            let sequential = garble_ckt(Circuit::try_from(&ir).unwrap(), labels.clone());
+            let parallel = garble_ckt_parallel(ckt, labels);
+            assert_eq!(
+                rkyv::to_bytes::<Error>(&sequential).unwrap().as_slice(),
+                rkyv::to_bytes::<Error>(&parallel).unwrap().as_slice()
+            );
+        }
+    }
+
+    #[test]
+    fn garbled_evaluation_matches_plaintext_simulation() {
+        use circuit_lib::random::RandomCircuitConfig;
+        use rand::{Rng, SeedableRng};
+        use rand_chacha::ChaCha12Rng;
+
+        let ir = circuit_lib::Circuit::random_with(&RandomCircuitConfig {
+            xor_ratio: 0.5,
+            inv_ratio: 0.1,
+            num_outputs: Some(16),
+            seed: 5,
+            ..RandomCircuitConfig::new(16, 500)
+        })
+        .unwrap()
+        .with_standard_layout()
+        .unwrap();
+        // the same function with LUT, constant and copy gates instead of INV
+        let absorbed = ir.absorb_inverters().with_standard_layout().unwrap();
+
+        let mut rng = ChaCha12Rng::seed_from_u64(9);
+        for ir in [ir, absorbed] {
+            let ckt = Circuit::try_from(&ir).unwrap();
+            let labels = crate::input::gen_labels(
+                [3u8; 32],
+                ckt.get_input_wire_count(),
+                ckt.get_inner_wire_count(),
+            );
+            let tables = garble_ckt(Circuit::try_from(&ir).unwrap(), labels.clone());
+            let inputs: Vec<Vec<bool>> = (0..256)
+                .map(|_| (0..16).map(|_| rng.random()).collect())
+                .collect();
+            let expected = ir.eval_bits_batch(&inputs).unwrap();
+            for (inputs, expected) in inputs.iter().zip(expected) {
+                assert_eq!(eval_garbled(&ckt, &labels, &tables, inputs), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn tables_ignoring_an_input_are_not_garbled_as_luts() {
+        // LUT0, LUTF, LUTC (a), LUTA (b), LUT3 (NOT a) and LUT5 (NOT b) of inputs 0 and 1
+        let tts = [0x0, 0xF, 0xC, 0xA, 0x3, 0x5];
+        let ir = circuit_lib::Circuit {
+            num_wires: 2 + tts.len(),
+            inputs: vec![0, 1],
+            outputs: (2..2 + tts.len()).collect(),
+            input_groups: vec![1, 1],
+            output_groups: vec![tts.len()],
+            gates: tts
+                .iter()
+                .enumerate()
+                .map(|(i, &tt)| circuit_lib::Gate {
+                    gate_type: circuit_lib::GateType::Lut(tt),
+                    inputs: vec![0, 1],
+                    output: 2 + i,
+                })
+                .collect(),
+        };
+        let ckt = Circuit::try_from(&ir).unwrap();
+        let labels = crate::input::gen_labels(
+            [4u8; 32],
+            ckt.get_input_wire_count(),
+            ckt.get_inner_wire_count(),
+        );
+        let tables = garble_ckt(Circuit::try_from(&ir).unwrap(), labels.clone());
+        assert!(tables.lut_tables.is_empty());
+        assert!(tables.and_tables.is_empty());
+        assert_eq!(tables.const_labels.len(), 2);
+        assert_eq!(tables.not_tables.len(), 2);
+
+        for x in 0..4 {
+            let inputs = [x & 2 != 0, x & 1 != 0];
+            assert_eq!(
+                eval_garbled(&ckt, &labels, &tables, &inputs),
+                ir.eval_bits(&inputs).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn stats_count_the_tables_garbling_builds() {
+        use circuit_lib::random::RandomCircuitConfig;
+
+        let ir = circuit_lib::Circuit::random_with(&RandomCircuitConfig {
+            xor_ratio: 0.5,
+            inv_ratio: 0.1,
+            seed: 7,
+            ..RandomCircuitConfig::new(16, 2000)
+        })
+        .unwrap();
+        let num_tables = ir.stats().unwrap().num_tables;
+
+        let ckt = Circuit::try_from(&ir.into_garbling_form().unwrap()).unwrap();
+        let labels = crate::input::gen_labels(
+            [5u8; 32],
+            ckt.get_input_wire_count(),
+            ckt.get_inner_wire_count(),
+        );
+        let tables = garble_ckt(ckt, labels);
+        assert!(num_tables > 0);
+        assert_eq!(
+            num_tables,
+            tables.and_tables.len() + tables.not_tables.len() + tables.lut_tables.len()
+        );
+    }
 }
@@ -4,7 +4,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::parse::parse_bristol;
+use crate::parse::parse_circuit;
+use circuit_lib::GateType;
 use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaCha12Rng;
 use rkyv::{Archive, Deserialize, Serialize};
@@ -21,6 +22,8 @@ pub struct Circuit {
     pub and_gate_count: usize,
     pub not_gate_count: usize,
     pub xor_gate_count: usize,
+    pub const_gate_count: usize,
+    pub lut_gate_count: usize,
     pub total_wire_count: usize,
     pub input1_count: usize,
     pub input2_count: usize,
@@ -34,9 +37,110 @@ impl Circuit {
         self.input1_count + self.input2_count
     }
 
-    /// Number of inner labels you must supply: one per AND and one per NOT
+    /// Number of inner labels you must supply: one per AND, NOT, constant and LUT
     pub fn get_inner_wire_count(&self) -> usize {
-        self.and_gate_count + self.not_gate_count
+        self.and_gate_count + self.not_gate_count + self.const_gate_count + self.lut_gate_count
+    }
+}
+
+/// Lossless conversion from the circuit-lib IR. The IR must use the standard Bristol layout
+/// (see `circuit_lib::Circuit::with_standard_layout`); the first input group is the garbler's
+/// input and the remaining groups are the evaluator's.
+impl TryFrom<&circuit_lib::Circuit> for Circuit {
+    type Error = anyhow::Error;
+
+    fn try_from(ckt: &circuit_lib::Circuit) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            ckt.has_standard_layout(),
+            "inputs must be the first wires and outputs the last ones, renumber the circuit first"
+        );
+        anyhow::ensure!(
+            ckt.input_groups.iter().sum::<usize>() == ckt.inputs.len(),
+            "input groups don't add up to the number of input wires"
+        );
+
+        let mut and_gate_count = 0;
+        let mut xor_gate_count = 0;
+        let mut not_gate_count = 0;
+        let mut const_gate_count = 0;
+        let mut lut_gate_count = 0;
+        let mut gates = Vec::with_capacity(ckt.gates.len());
+        for g in &ckt.gates {
+            let out = g.output;
+            let gate = match (g.gate_type, g.inputs.as_slice()) {
+                // every 2-input gate is lowered through its truth table, so e.g. an OR is
+                // garbled with a single table and XNOR is free. Tables that ignore an input are
+                // a constant, a copy or an INV, which binary files may hold unfolded
+                (gate_type, &[in0, in1]) if gate_type.arity() == 2 => {
+                    match gate_type
+                        .truth_table()
+                        .expect("2-input gates have a truth table")
+                    {
+                        0x8 => {
+                            and_gate_count += 1;
+                            GateDef::And { in0, in1, out }
+                        }
+                        0x6 => {
+                            xor_gate_count += 1;
+                            GateDef::Xor { in0, in1, out }
+                        }
+                        0x9 => GateDef::Xnor { in0, in1, out },
+                        tt @ (0x0 | 0xF) => {
+                            const_gate_count += 1;
+                            GateDef::Const {
+                                value: tt == 0xF,
+                                out,
+                            }
+                        }
+                        0xC => GateDef::Buf { input: in0, out },
+                        0xA => GateDef::Buf { input: in1, out },
+                        0x3 => {
+                            not_gate_count += 1;
+                            GateDef::Not { input: in0, out }
+                        }
+                        0x5 => {
+                            not_gate_count += 1;
+                            GateDef::Not { input: in1, out }
+                        }
+                        truth_table => {
+                            lut_gate_count += 1;
+                            GateDef::Lut {
+                                truth_table,
+                                in0,
+                                in1,
+                                out,
+                            }
+                        }
+                    }
+                }
+                (GateType::Inv, &[input]) => {
+                    not_gate_count += 1;
+                    GateDef::Not { input, out }
+                }
+                (GateType::Const(value), &[]) => {
+                    const_gate_count += 1;
+                    GateDef::Const { value, out }
+                }
+                (GateType::Buf, &[input]) => GateDef::Buf { input, out },
+                (gate_type, _) => anyhow::bail!("malformed {gate_type} gate driving wire {out}"),
+            };
+            gates.push(gate);
+        }
+
+        let input1_count = ckt.input_groups.first().copied().unwrap_or(0);
+        Ok(Circuit {
+            total_gate_count: gates.len(),
+            and_gate_count,
+            not_gate_count,
+            xor_gate_count,
+            const_gate_count,
+            lut_gate_count,
+            total_wire_count: ckt.num_wires,
+            input1_count,
+            input2_count: ckt.inputs.len() - input1_count,
+            output_wire_count: ckt.outputs.len(),
+            gates,
+        })
     }
 }
 
@@ -52,9 +156,71 @@ pub struct WireLabels {
     Debug, Archive, Serialize, Deserialize, PartialEq, serde::Serialize, serde::Deserialize,
 )]
 pub enum GateDef {
-    And { in0: usize, in1: usize, out: usize },
-    Xor { in0: usize, in1: usize, out: usize },
-    Not { input: usize, out: usize },
+    And {
+        in0: usize,
+        in1: usize,
+        out: usize,
+    },
+    Xor {
+        in0: usize,
+        in1: usize,
+        out: usize,
+    },
+    Not {
+        input: usize,
+        out: usize,
+    },
+    /// constant wire, its label is published by the garbler
+    Const {
+        value: bool,
+        out: usize,
+    },
+    /// copy of the input wire, shares its labels
+    Buf {
+        input: usize,
+        out: usize,
+    },
+    /// negated XOR, free like XOR
+    Xnor {
+        in0: usize,
+        in1: usize,
+        out: usize,
+    },
+    /// any other 2-input gate, bit `2a + b` of `truth_table` is the output for inputs `(a, b)`
+    Lut {
+        truth_table: u8,
+        in0: usize,
+        in1: usize,
+        out: usize,
+    },
+}
+
+impl GateDef {
+    /// The wires the gate reads
+    pub fn inputs(&self) -> impl Iterator<Item = usize> + use<> {
+        let (a, b) = match *self {
+            GateDef::And { in0, in1, .. }
+            | GateDef::Xor { in0, in1, .. }
+            | GateDef::Xnor { in0, in1, .. }
+            | GateDef::Lut { in0, in1, .. } => (Some(in0), Some(in1)),
+            GateDef::Not { input, .. } | GateDef::Buf { input, .. } => (Some(input), None),
+            GateDef::Const { .. } => (None, None),
+        };
+        a.into_iter().chain(b)
+    }
+
+    /// The wire the gate drives
+    pub fn output(&self) -> usize {
+        match *self {
+            GateDef::And { out, .. }
+            | GateDef::Xor { out, .. }
+            | GateDef::Not { out, .. }
+            | GateDef::Const { out, .. }
+            | GateDef::Buf { out, .. }
+            | GateDef::Xnor { out, .. }
+            | GateDef::Lut { out, .. } => out,
+        }
+    }
 }
 
 #[derive(Archive, Serialize, Deserialize, Clone, serde::Serialize, serde::Deserialize)]
@@ -63,7 +229,7 @@ pub struct LabelInputs {
     pub delta: Label,
     // zero labels for input wires
     pub input_labels: Vec<Label>,
-    // zero labels for output of AND and NOT gates
+    // zero labels for output of AND, NOT and constant gates
     pub inner_labels: Vec<Label>,
 }
 
@@ -98,9 +264,9 @@ pub fn gen_labels(seed: [u8; 32], input_wire_count: usize, inner_wire_count: usi
     }
 }
 
-/// read the circuit
-pub fn read_input_ckt(path_to_bristol: PathBuf) -> Circuit {
-    parse_bristol(path_to_bristol).unwrap()
+/// read the circuit, a Bristol Fashion or binary file
+pub fn read_input_ckt(path_to_circuit: PathBuf) -> Circuit {
+    parse_circuit(path_to_circuit).unwrap()
 }
 
 pub fn load_seed<P: AsRef<Path>>(path: P) -> io::Result<[u8; 32]> {
@@ -1,4 +1,18 @@
+pub mod digest;
 pub mod garble;
 pub mod input;
 pub mod output;
 pub mod parse;
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use std::path::PathBuf;
+
+    /// `circuits/<name>/<name>.bristol`, one of the example circuits shipped with the repo
+    pub(crate) fn example_path(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "../../circuits", name]
+            .iter()
+            .collect::<PathBuf>()
+            .join(format!("{name}.bristol"))
+    }
+}
@@ -5,6 +5,8 @@ use rkyv::{Archive, Deserialize, Serialize};
 pub struct GarbledTables {
     pub and_tables: Vec<AndGateTable>,
     pub not_tables: Vec<NotGateTable>,
+    pub const_labels: Vec<ConstLabel>,
+    pub lut_tables: Vec<LutGateTable>,
 }
 
 // #[derive(serde::Serialize, serde::Deserialize)]
@@ -26,3 +28,26 @@ pub struct NotGateTable {
     pub out: usize,
     pub table: [[u8; 16]; 2],
 }
+
+/// Garbled table of an arbitrary 2-input gate
+// #[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Archive, Serialize, Deserialize, Debug)]
+pub struct LutGateTable {
+    pub gate: usize,
+    pub truth_table: u8,
+    pub in0: usize,
+    pub in1: usize,
+    pub out: usize,
+    // four ciphertexts ordered (a=0,b=0) .. (1,1)
+    pub table: [[u8; 16]; 4],
+}
+
+/// The label of a constant wire for its constant value, published so the evaluator can use it.
+/// Only the matching label is revealed, the other one would leak delta.
+// #[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Archive, Serialize, Deserialize)]
+pub struct ConstLabel {
+    pub gate: usize,
+    pub out: usize,
+    pub label: [u8; 16],
+}
@@ -1,9 +1,9 @@
 use crate::input::Circuit;
-use crate::input::GateDef;
+use circuit_lib::format::Format;
 use std::path::Path;
 use std::{
     fs::File,
-    io::{self, BufRead, BufReader, Read},
+    io::{self, Read},
 };
 
 pub fn load_seed<P: AsRef<Path>>(path: P) -> io::Result<[u8; 32]> {
@@ -16,118 +16,45 @@ pub fn load_seed<P: AsRef<Path>>(path: P) -> io::Result<[u8; 32]> {
     Ok(seed)
 }
 
+/// Parse a Bristol Fashion file into the garbling representation. The I/O layout is taken
+/// from the header: the first input value belongs to the garbler, the rest to the evaluator.
+/// Constants are folded and inverters absorbed first, so gates fed by `EQ` wires and INV gates
+/// cost no tables. The circuit hash commits to the result, not to the gates of the file.
 pub fn parse_bristol<P: AsRef<Path>>(path_to_bristol: P) -> anyhow::Result<Circuit> {
-    let file = File::open(path_to_bristol)?;
-    let mut rdr = BufReader::new(file);
-    let mut line = String::new();
-
-    // Parse header_line1: <num_gates> <num_wires>
-    rdr.read_line(&mut line)?;
-    let mut parts = line.split_whitespace();
-    let total_gate_count: usize = parts.next().unwrap().parse()?;
-    let total_wire_count: usize = parts.next().unwrap().parse()?;
-    line.clear();
-
-    // Parse header_line2: Input description
-    // TODO: (mukesh) considers only 1 input, generalize it.
-    rdr.read_line(&mut line)?;
-    let mut ins = line.split_whitespace();
-    let _num_inputs: usize = ins.next().unwrap().parse()?;
-    let input1_count: usize = ins.next().unwrap().parse()?;
-    let input2_count: usize = 0;
-    line.clear();
-
-    // Parse header_line3: Output description
-    // TODO: (mukesh) considers only 1 output, generalize it.
-    rdr.read_line(&mut line)?;
-    let mut ins = line.split_whitespace();
-    let _num_outputs: usize = ins.next().unwrap().parse()?;
-    let output_wire_count: usize = ins.next().unwrap().parse()?;
-    line.clear();
-
-    // store all the gates
-    let mut gates = Vec::with_capacity(total_gate_count);
-
-    // initialize variables to get the gate counts
-    let mut and_gate_count: usize = 0;
-    let mut xor_gate_count: usize = 0;
-    let mut not_gate_count: usize = 0;
-
-    // loop over lines in the ckt to parse the gate
-    for _ in 0..total_gate_count {
-        rdr.read_line(&mut line)?;
-
-        let mut p = line.split_whitespace();
-
-        let fan_in: usize = p.next().unwrap().parse()?;
-
-        let _fan_out: usize = p.next().unwrap().parse()?;
-
-        let in0: usize = p.next().unwrap().parse()?;
-
-        let in1: Option<usize> = if fan_in == 1 {
-            None
-        } else {
-            Some(p.next().unwrap().parse()?)
-        };
-
-        let output = p.next().unwrap().parse()?;
-
-        let gstr = p.next().unwrap();
+    let parsed = circuit_lib::Circuit::from_bristol_fashion(path_to_bristol.as_ref())?;
+    Circuit::try_from(&parsed.into_garbling_form()?)
+}
 
-        match gstr {
-            "AND" => and_gate_count += 1,
-            "XOR" => xor_gate_count += 1,
-            "INV" => not_gate_count += 1,
-            other => {
-                anyhow::bail!("unexpected gate `{}`", other)
-            }
+/// Load a circuit file into the garbling representation: a binary circuit as is, the text
+/// formats of [`circuit_lib::format`] prepared as in [`parse_bristol`]. A binary file is the
+/// circuit's canonical encoding, so it is not rewritten and its SHA-256 is the circuit hash;
+/// it must then already be in evaluation order, which the garbler relies on.
+pub fn parse_circuit<P: AsRef<Path>>(path: P) -> anyhow::Result<Circuit> {
+    let path = path.as_ref();
+    match Format::detect(path)? {
+        Format::Binary => {
+            let ckt = circuit_lib::Circuit::from_binary_file(path)?;
+            anyhow::ensure!(
+                ckt.is_in_evaluation_order(),
+                "a gate of `{}` reads a wire that is neither an input nor driven by an earlier \
+                 gate, rewrite it with `circuit-utils convert --to binary`",
+                path.display()
+            );
+            Circuit::try_from(&ckt)
+        }
+        format => {
+            let parsed = circuit_lib::Circuit::from_file_as(path, format)?;
+            Circuit::try_from(&parsed.into_garbling_form()?)
         }
-
-        let gate = match gstr {
-            "AND" => GateDef::And {
-                in0,
-                in1: in1.unwrap(),
-                out: output,
-            },
-            "XOR" => GateDef::Xor {
-                in0,
-                in1: in1.unwrap(),
-                out: output,
-            },
-            "INV" => GateDef::Not {
-                input: in0,
-                out: output,
-            },
-            other => {
-                anyhow::bail!("unexpected gate `{}`", other)
-            }
-        };
-        gates.push(gate);
-        line.clear();
     }
-
-    // TODO: @mukesh (sanity checks) The gate and wire counts as claimed by header and in the circuit
-    // may be inconsistent. Can we trust the input? It might be better to either validate them, or
-    // compute them from circuit description as needed. For now, its ok to have all values
-    // once the garbling code is done, this can be updated.
-    let ckt = Circuit {
-        total_gate_count,
-        and_gate_count,
-        not_gate_count,
-        xor_gate_count,
-        total_wire_count,
-        input1_count,
-        input2_count,
-        output_wire_count,
-        gates,
-    };
-    Ok(ckt)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::garble::garble_ckt;
+    use crate::input::{GateDef, gen_labels};
+    use crate::test_util::example_path;
     use rand::rand_core::{OsRng, TryRngCore};
     use std::{fs, path::PathBuf};
 
@@ -164,4 +91,136 @@ mod tests {
         let raw = fs::read(&path).expect("Failed to read raw bytes");
         assert_eq!(&seed[..], &raw[..], "Loaded seed must match file contents");
     }
+
+    #[test]
+    fn test_parse_example1() {
+        let ckt = parse_bristol(example_path("example1")).unwrap();
+        assert_eq!(ckt.total_gate_count, 4);
+        assert_eq!(ckt.and_gate_count, 2);
+        assert_eq!(ckt.xor_gate_count, 2);
+        assert_eq!(ckt.not_gate_count, 0);
+        assert_eq!(ckt.total_wire_count, 9);
+        assert_eq!(ckt.input1_count, 5);
+        assert_eq!(ckt.input2_count, 0);
+        assert_eq!(ckt.output_wire_count, 1);
+        assert_eq!(
+            ckt.gates[3],
+            GateDef::And {
+                in0: 7,
+                in1: 4,
+                out: 8
+            }
+        );
+    }
+
+    #[test]
+    fn test_constants_and_inverters_are_removed_before_garbling() {
+        // out = NOT(a AND 1) XOR b = (a XOR b) XOR 1
+        let path = std::env::temp_dir().join("garble_parse_constants.bristol");
+        fs::write(
+            &path,
+            "4 7\n2 1 1\n1 1\n\n1 1 1 2 EQ\n2 1 0 2 3 AND\n1 1 3 4 INV\n2 1 4 1 6 XOR\n",
+        )
+        .unwrap();
+        let ckt = parse_bristol(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(ckt.and_gate_count, 0);
+        assert_eq!(ckt.not_gate_count, 0);
+        assert_eq!(ckt.const_gate_count, 1);
+        assert_eq!(ckt.total_wire_count, 5);
+        assert_eq!(
+            ckt.gates,
+            vec![
+                GateDef::Xor {
+                    in0: 0,
+                    in1: 1,
+                    out: 2
+                },
+                GateDef::Const {
+                    value: true,
+                    out: 3
+                },
+                GateDef::Xor {
+                    in0: 2,
+                    in1: 3,
+                    out: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gates_out_of_order_are_sorted() {
+        // example1 with its gates reversed and sparse wire ids
+        let path = std::env::temp_dir().join("garble_parse_unordered.bristol");
+        fs::write(
+            &path,
+            "4 90\n1 5\n1 1\n\n2 1 70 4 89 AND\n2 1 50 60 70 XOR\n2 1 2 3 60 XOR\n2 1 0 1 50 AND\n",
+        )
+        .unwrap();
+        let ckt = parse_bristol(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(ckt.total_wire_count, 9);
+        assert_eq!(
+            ckt.canonical_hash(),
+            parse_bristol(example_path("example1"))
+                .unwrap()
+                .canonical_hash()
+        );
+    }
+
+    #[test]
+    fn test_binary_gates_out_of_order_are_rejected() {
+        let ir = circuit_lib::Circuit::from_bristol_fashion(&example_path("example1")).unwrap();
+        let path = std::env::temp_dir().join("garble_parse_unordered.bin");
+        let write = |ir: &circuit_lib::Circuit| {
+            let mut bytes = Vec::new();
+            ir.write_binary(&mut bytes).unwrap();
+            fs::write(&path, bytes).unwrap();
+            parse_circuit(&path)
+        };
+        assert!(write(&ir).is_ok());
+
+        let mut reversed = ir.clone();
+        reversed.gates.reverse();
+        let reversed = write(&reversed);
+        // without XOR 2 3 -> 6, wire 6 is read but never driven
+        let mut undriven = ir;
+        undriven.gates.remove(1);
+        let undriven = write(&undriven);
+        fs::remove_file(&path).unwrap();
+        assert!(reversed.is_err());
+        assert!(undriven.is_err());
+    }
+
+    #[test]
+    fn test_input_groups_split_between_parties() {
+        let mut ir = circuit_lib::Circuit::from_bristol_fashion(&example_path("example1")).unwrap();
+        ir.input_groups = vec![2, 3];
+        let ckt = Circuit::try_from(&ir).unwrap();
+        assert_eq!(ckt.input1_count, 2);
+        assert_eq!(ckt.input2_count, 3);
+    }
+
+    #[test]
+    fn test_random_circuit_garbles_after_relabeling() {
+        let ir = circuit_lib::Circuit::random(8, 200, 0.7, 1);
+        let ir = if ir.has_standard_layout() {
+            ir
+        } else {
+            assert!(Circuit::try_from(&ir).is_err());
+            ir.with_standard_layout().unwrap()
+        };
+
+        let ckt = Circuit::try_from(&ir).unwrap();
+        assert_eq!(ckt.total_gate_count, 200);
+        assert_eq!(ckt.get_input_wire_count(), 8);
+
+        let labels = gen_labels([7u8; 32], 8, ckt.get_inner_wire_count());
+        let and_gate_count = ckt.and_gate_count;
+        let tables = garble_ckt(ckt, labels);
+        assert_eq!(tables.and_tables.len(), and_gate_count);
+    }
 }